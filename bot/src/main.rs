@@ -0,0 +1,179 @@
+/// Main function of the example bot: answers to !ping, !echo and !roll
+// Necessary imports
+use chat_lib::{
+    bot::{Bot, BotRunner, Command},
+    client::{fetch_policy, resolve},
+    config::LogConfig,
+    logging,
+    messages::{Message, DEFAULT_ROOM},
+    shutdown, IP_ADDR, PORT,
+};
+use std::{
+    net::IpAddr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use structopt::StructOpt;
+use tokio::select;
+use tracing::info;
+
+/// Maximum number of dice of a roll
+const MAX_DICE: u32 = 100;
+
+/// Maximum number of faces of a die
+const MAX_FACES: u32 = 1000;
+
+/// BotArgs struct representing the cli arguments of the bot
+#[derive(StructOpt, Debug)]
+#[structopt(name = "Chat-TUI bot")]
+pub struct BotArgs {
+    /// Ip: Server ip address to connect to
+    #[structopt(
+        short,
+        long,
+        about = "Server ip address to connect to (default: 127.0.0.1)"
+    )]
+    pub ip: Option<IpAddr>,
+
+    /// Host: Hostname of the server, overrides --ip
+    #[structopt(short = "H", long, about = "Hostname of the server, overrides --ip")]
+    pub host: Option<String>,
+
+    /// Port: Server port to connect to
+    #[structopt(short, long, about = "Server port to connect to (default: 8080)")]
+    pub port: Option<u16>,
+
+    /// Name: Username of the bot
+    #[structopt(short, long, default_value = "dicebot", about = "Username of the bot")]
+    pub name: String,
+
+    /// Rooms: Rooms the bot joins (the default room if none)
+    #[structopt(
+        short,
+        long = "room",
+        about = "Room the bot joins, can be repeated (default: general)"
+    )]
+    pub rooms: Vec<String>,
+}
+
+/// DiceBot struct representing a bot echoing messages and rolling dice
+#[derive(Debug)]
+pub struct DiceBot {
+    /// Username of the bot
+    name: String,
+
+    /// Rooms of the bot
+    rooms: Vec<String>,
+
+    /// State of the random number generator (xorshift)
+    seed: u64,
+}
+
+// Methods for the DiceBot struct
+impl DiceBot {
+    /// Constructor method, the random number generator is seeded with the current time
+    ///
+    /// Args:
+    ///     - name: username of the bot
+    ///     - rooms: rooms of the bot
+    pub fn new(name: String, rooms: Vec<String>) -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+
+        Self {
+            name,
+            rooms,
+            seed: seed | 1,
+        }
+    }
+
+    // Random method returns a number between 1 and max (included)
+    fn random(&mut self, max: u32) -> u32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        (self.seed % u64::from(max)) as u32 + 1
+    }
+
+    // Roll method rolls the dice described like "2d6" (one six-sided die if None)
+    fn roll(&mut self, dice: Option<&str>) -> Result<String, String> {
+        let dice = dice.unwrap_or("1d6").to_lowercase();
+        let (count, faces) = dice
+            .split_once('d')
+            .ok_or_else(|| format!("Invalid dice: {dice}, try 2d6"))?;
+        let count = match count {
+            "" => 1,
+            count => count.parse().map_err(|_| format!("Invalid dice: {dice}"))?,
+        };
+        let faces: u32 = faces.parse().map_err(|_| format!("Invalid dice: {dice}"))?;
+
+        if !(1..=MAX_DICE).contains(&count) || !(2..=MAX_FACES).contains(&faces) {
+            return Err(format!("Up to {MAX_DICE} dice with 2 to {MAX_FACES} faces"));
+        }
+
+        let rolls: Vec<u32> = (0..count).map(|_| self.random(faces)).collect();
+        let total: u32 = rolls.iter().sum();
+        let rolls: Vec<String> = rolls.iter().map(u32::to_string).collect();
+
+        Ok(match count {
+            1 => format!("rolled {dice}: {total}"),
+            _ => format!("rolled {dice}: {} = {total}", rolls.join(" + ")),
+        })
+    }
+}
+
+// Bot implementation for DiceBot struct
+impl Bot for DiceBot {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn rooms(&self) -> Vec<String> {
+        self.rooms.clone()
+    }
+
+    fn on_command(&mut self, command: &Command, message: &Message) -> Option<String> {
+        match command.name.as_str() {
+            "ping" => Some("pong".to_string()),
+            "echo" if !command.args.is_empty() => Some(command.args.join(" ")),
+            "roll" => Some(match self.roll(command.args.first().map(String::as_str)) {
+                Ok(result) => format!("@{} {result}", message.user),
+                Err(e) => e,
+            }),
+            "help" => Some("Commands: !ping, !echo <text>, !roll [NdM]".to_string()),
+            _ => None,
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    logging::init(&LogConfig::default())?; // Logging, RUST_LOG sets the level
+
+    let args = BotArgs::from_args();
+    let host = args
+        .host
+        .unwrap_or_else(|| args.ip.unwrap_or(IP_ADDR).to_string());
+    let socket_addr = resolve(&host, args.port.unwrap_or(PORT)).await?;
+
+    // The name and the rooms of the bot must follow the rules of the server
+    let policy = fetch_policy(socket_addr).await.unwrap_or_default();
+    policy.validate_name("Username", &args.name)?;
+    let rooms = match args.rooms {
+        rooms if rooms.is_empty() => vec![DEFAULT_ROOM.to_string()],
+        rooms => rooms,
+    };
+    for room in &rooms {
+        policy.validate_name("Room", room)?;
+    }
+
+    let runner = BotRunner::new(DiceBot::new(args.name, rooms), socket_addr);
+    select! {
+        result = runner.run() => result,
+        signal = shutdown::signal() => {
+            info!("Received {}, stopping", signal?);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,203 @@
+/// Admin module: moderation endpoints reserved to the holders of an admin token
+// Necessary imports
+use crate::{
+    context::Context,
+    import::{import, parse},
+    incoming::IncomingWebhook,
+    messages::Message,
+    moderation::{Sanction, SanctionKind},
+    request::{decode_component, Method, Request},
+    response::{Response, Status},
+};
+use subtle::ConstantTimeEq;
+
+/// Get response function generates the response of a request to the admin api (/admin/...)
+///
+/// Args:
+///     - req: request from the client
+///     - ctx: context of the server with the messages and the configuration
+pub async fn get_response(req: &Request, ctx: &Context) -> Result<Response, anyhow::Error> {
+    // The admin token is sent as a bearer token
+    let token = req
+        .header("Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match token {
+        None => {
+            return Ok(Response::new(
+                Status::Unauthorized,
+                "text/plain",
+                "Missing admin token".to_string(),
+            )
+            .header("WWW-Authenticate", "Bearer"));
+        }
+        Some(token)
+            if !ctx
+                .config
+                .admin
+                .tokens
+                .iter()
+                .any(|admin| token_eq(admin, token)) =>
+        {
+            return Ok(Response::new(
+                Status::Forbidden,
+                "text/plain",
+                "Invalid admin token".to_string(),
+            ));
+        }
+        Some(_) => {}
+    }
+
+    let segments: Vec<&str> = req
+        .path()
+        .trim_start_matches("/admin/")
+        .split('/')
+        .collect();
+    let (status, body) = match (req.method, segments.as_slice()) {
+        // Check of the admin token
+        (Method::Get, ["check"]) => (Status::Ok, r#"{"status":"ok"}"#.to_string()),
+        // Delete a message
+        (Method::Delete, ["messages", id]) => match id.parse::<u64>() {
+            Ok(id) => match remove(ctx, |msg| msg.id == id).await? {
+                0 => (Status::NotFound, format!("Message {id} not found")),
+                _ => (Status::Ok, r#"{"status":"ok","deleted":1}"#.to_string()),
+            },
+            Err(_) => (Status::BadRequest, format!("Invalid message id: {id}")),
+        },
+        // Clear a room
+        (Method::Delete, ["rooms", room]) => {
+            let room = decode_component(room);
+            let deleted = remove(ctx, |msg| msg.room == room).await?;
+            (
+                Status::Ok,
+                format!(r#"{{"status":"ok","deleted":{deleted}}}"#),
+            )
+        }
+        // Mute, ban, unmute or unban a user or an address
+        (Method::Post, [action @ ("mute" | "ban" | "unmute" | "unban")]) => {
+            let sanction = req
+                .body
+                .as_deref()
+                .map(serde_json::from_str::<Sanction>)
+                .transpose();
+            let kind = match *action {
+                "mute" | "unmute" => SanctionKind::Mute,
+                _ => SanctionKind::Ban,
+            };
+
+            let result = match sanction {
+                Ok(Some(sanction)) if action.starts_with("un") => {
+                    ctx.moderation.lift(kind, &sanction).await.map(|lifted| {
+                        if lifted {
+                            (Status::Ok, r#"{"status":"ok"}"#.to_string())
+                        } else {
+                            (Status::NotFound, format!("No active {action} target found"))
+                        }
+                    })
+                }
+                Ok(Some(sanction)) => ctx
+                    .moderation
+                    .apply(kind, &sanction)
+                    .await
+                    .map(|_| (Status::Ok, r#"{"status":"ok"}"#.to_string())),
+                Ok(None) => Err(anyhow::anyhow!("Missing sanction")),
+                Err(e) => Err(anyhow::anyhow!("Invalid sanction: {e}")),
+            };
+            result.unwrap_or_else(|e| (Status::BadRequest, e.to_string()))
+        }
+        // Reload the content filters from their file
+        (Method::Post, ["filters", "reload"]) => match ctx.filters.reload() {
+            Ok(count) => (
+                Status::Ok,
+                format!(r#"{{"status":"ok","filters":{count}}}"#),
+            ),
+            Err(e) => (Status::BadRequest, format!("{e:#}")),
+        },
+        // Import the messages of a JSON Lines transcript, keeping their ids
+        (Method::Post, ["import"]) => {
+            let imported = req
+                .body
+                .as_deref()
+                .ok_or_else(|| anyhow::anyhow!("Missing transcript"))
+                .and_then(|content| parse(content, &ctx.config.validation));
+            match imported {
+                Ok(imported) => {
+                    let report = import(ctx, imported).await?;
+                    (
+                        Status::Ok,
+                        format!(
+                            r#"{{"status":"ok","imported":{},"duplicates":{}}}"#,
+                            report.imported,
+                            serde_json::to_string(&report.duplicates)?
+                        ),
+                    )
+                }
+                Err(e) => (Status::BadRequest, format!("{e:#}")),
+            }
+        }
+        // List, create or revoke the incoming webhooks
+        (Method::Get, ["hooks"]) => (Status::Ok, serde_json::to_string(&ctx.incoming.list())?),
+        (Method::Post, ["hooks"]) => {
+            let policy = &ctx.config.validation;
+            let webhook = req
+                .body
+                .as_deref()
+                .ok_or_else(|| anyhow::anyhow!("Missing webhook"))
+                .and_then(|body| {
+                    serde_json::from_str::<IncomingWebhook>(body)
+                        .map_err(|e| anyhow::anyhow!("Invalid webhook: {e}"))
+                })
+                .and_then(|webhook| {
+                    policy.validate_name("Username", &webhook.user)?;
+                    policy.validate_name("Room", &webhook.room)?;
+                    Ok(webhook)
+                });
+            match webhook {
+                Ok(webhook) => (
+                    Status::Ok,
+                    serde_json::to_string(&ctx.incoming.create(webhook)?)?,
+                ),
+                Err(e) => (Status::BadRequest, e.to_string()),
+            }
+        }
+        (Method::Delete, ["hooks", token]) => match ctx.incoming.revoke(token)? {
+            true => (Status::Ok, r#"{"status":"ok"}"#.to_string()),
+            false => (Status::NotFound, "Webhook not found".to_string()),
+        },
+        _ => (Status::NotFound, "Not Found".to_string()),
+    };
+
+    let content_type = match status {
+        Status::Ok => "application/json",
+        _ => "text/plain",
+    };
+    Ok(Response::new(status, content_type, body))
+}
+
+/// Token eq function compares a secret token in constant time, so that the time of the comparison
+/// doesn't reveal how much of a guessed token is right
+///
+/// Args:
+///     - expected: token held by the server
+///     - given: token sent by the client
+pub fn token_eq(expected: &str, given: &str) -> bool {
+    expected.as_bytes().ct_eq(given.as_bytes()).into()
+}
+
+// Remove function deletes the messages matching the predicate from the state and the store, returns how many were deleted
+async fn remove<F>(ctx: &Context, predicate: F) -> Result<usize, anyhow::Error>
+where
+    F: Fn(&Message) -> bool,
+{
+    let mut msgs = ctx.state.lock().await;
+    let len = msgs.len();
+    msgs.retain(|msg| !predicate(msg));
+
+    let deleted = len - msgs.len();
+    if deleted > 0
+        && let Some(store) = &ctx.store
+    {
+        store.rewrite(&msgs).await?;
+    }
+
+    Ok(deleted)
+}
@@ -1,8 +1,9 @@
 /// Args module
 // Necessary imports
+use std::{net::IpAddr, path::PathBuf};
 use structopt::StructOpt;
 
-/// Args struct representing cli arguments
+// Args struct representing cli arguments (a plain comment: structopt would show a doc comment in the help of the binaries)
 #[derive(StructOpt, Debug)]
 #[structopt(name = "Chat-TUI")]
 pub struct Args {
@@ -11,10 +12,19 @@ pub struct Args {
         long,
         about = "Specify a different ip address than default (localhost 127.0.0.1)"
     )]
-    /// Ip: Specify a different ip address than default (localhost 127.0.0.1)
-    pub ip: Option<String>,
+    /// Ip: Specify a different ip address than default (localhost 127.0.0.1), IPv6 addresses are supported
+    pub ip: Option<IpAddr>,
 
     /// Port: Specify a different port than default (8080)
     #[structopt(short, long, about = "Specify a different port than default (8080)")]
-    pub port: Option<String>,
+    pub port: Option<u16>,
+
+    /// Config: Path of the TOML config file
+    #[structopt(
+        short,
+        long,
+        parse(from_os_str),
+        about = "Path of the TOML config file"
+    )]
+    pub config: Option<PathBuf>,
 }
@@ -0,0 +1,239 @@
+/// Bot module: framework to write bots reacting to the messages of the chat
+// Necessary imports
+use crate::{
+    client::{fetch_messages, send_message, Backoff, SendError},
+    config::RateConfig,
+    messages::{Message, DEFAULT_ROOM},
+    rate_limit::RateLimiter,
+};
+use std::{collections::HashMap, net::SocketAddr, time::Duration};
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+/// Bot trait implemented by the bots: the runner calls it for every new message of the rooms the bot is in,
+/// the returned text is sent back as a reply (as a direct message if the message was direct)
+pub trait Bot: Send {
+    /// Name method returns the username of the bot
+    fn name(&self) -> &str;
+
+    /// Rooms method returns the rooms the bot reads, only the default room if not implemented
+    fn rooms(&self) -> Vec<String> {
+        vec![DEFAULT_ROOM.to_string()]
+    }
+
+    /// On message method is called for the messages that are not commands
+    ///
+    /// Args:
+    ///     - message: new message
+    fn on_message(&mut self, message: &Message) -> Option<String> {
+        let _ = message;
+        None
+    }
+
+    /// On command method is called for the messages starting with the command prefix (e.g. !ping)
+    ///
+    /// Args:
+    ///     - command: command parsed from the message
+    ///     - message: message containing the command
+    fn on_command(&mut self, command: &Command, message: &Message) -> Option<String> {
+        let _ = (command, message);
+        None
+    }
+}
+
+/// Command struct representing a command sent to a bot, like "!roll 2d6"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Command {
+    /// Name of the command, lowercase and without the prefix
+    pub name: String,
+
+    /// Arguments of the command, split on whitespace
+    pub args: Vec<String>,
+}
+
+// Methods for the Command struct
+impl Command {
+    /// Parse method reads a command from the text of a message, returns None if the text is not a command
+    ///
+    /// Args:
+    ///     - text: text of the message
+    ///     - prefix: character starting the commands
+    pub fn parse(text: &str, prefix: char) -> Option<Self> {
+        let mut words = text.strip_prefix(prefix)?.split_whitespace();
+        let name = words.next()?.to_lowercase();
+
+        Some(Self {
+            name,
+            args: words.map(String::from).collect(),
+        })
+    }
+}
+
+/// BotRunner struct running a bot against a server: it polls the rooms of the bot, reconnects with
+/// exponential backoff when the server is not reachable and limits how fast the replies are sent
+#[derive(Debug)]
+pub struct BotRunner<B> {
+    /// Bot to run
+    bot: B,
+
+    /// Socket address of the server
+    socket_addr: SocketAddr,
+
+    /// Delay between two polls of the rooms
+    poll_interval: Duration,
+
+    /// Character starting the commands
+    prefix: char,
+
+    /// Limiter of the replies sent by the bot
+    limiter: RateLimiter<()>,
+}
+
+// Methods for the BotRunner struct
+impl<B: Bot> BotRunner<B> {
+    /// Constructor method: polls every second, commands start with '!' and the replies are limited to 20 per minute
+    ///
+    /// Args:
+    ///     - bot: bot to run
+    ///     - socket_addr: socket address of the server
+    pub fn new(bot: B, socket_addr: SocketAddr) -> Self {
+        Self {
+            bot,
+            socket_addr,
+            poll_interval: Duration::from_secs(1),
+            prefix: '!',
+            limiter: RateLimiter::new(&RateConfig {
+                per_minute: 20,
+                burst: 5,
+            }),
+        }
+    }
+
+    /// Poll interval method sets the delay between two polls of the rooms
+    ///
+    /// Args:
+    ///     - poll_interval: delay between two polls
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Prefix method sets the character starting the commands
+    ///
+    /// Args:
+    ///     - prefix: character starting the commands
+    pub fn prefix(mut self, prefix: char) -> Self {
+        self.prefix = prefix;
+        self
+    }
+
+    /// Rate method sets how fast the replies can be sent (0 messages per minute disables the limit)
+    ///
+    /// Args:
+    ///     - rate: rate of the replies
+    pub fn rate(mut self, rate: &RateConfig) -> Self {
+        self.limiter = RateLimiter::new(rate);
+        self
+    }
+
+    /// Run method runs the bot until the task is dropped: the messages sent before the bot started are ignored,
+    /// the ones sent while the server was unreachable are handled once it is back
+    pub async fn run(mut self) -> Result<(), anyhow::Error> {
+        let name = self.bot.name().to_string();
+        let rooms = self.bot.rooms();
+        info!(bot = %name, ?rooms, "bot started");
+
+        // Id of the last message handled in every room, missing until the room is read the first time
+        let mut last_ids: HashMap<String, u64> = HashMap::new();
+        let mut backoff = Backoff::default();
+
+        loop {
+            let mut replies = Vec::new();
+            let mut failure = None;
+
+            for room in &rooms {
+                let messages = match fetch_messages(self.socket_addr, room, &name).await {
+                    Ok(messages) => messages,
+                    Err(e) => {
+                        failure = Some(e);
+                        break;
+                    }
+                };
+                let newest = messages.last().map_or(0, |msg| msg.id);
+
+                if let Some(&last_id) = last_ids.get(room) {
+                    for msg in messages
+                        .iter()
+                        .filter(|msg| msg.id > last_id && msg.user != name)
+                    {
+                        if let Some(reply) = self.handle(msg) {
+                            replies.push(reply);
+                        }
+                    }
+                }
+                // A server restarted without storage starts again from the first id, the next ones are handled normally
+                last_ids.insert(room.clone(), newest);
+            }
+
+            for reply in replies {
+                self.send(reply).await;
+            }
+
+            match failure {
+                Some(e) => {
+                    let delay = backoff.next_delay();
+                    warn!(
+                        %e,
+                        attempt = backoff.attempt(),
+                        "server unreachable, retrying in {delay:?}"
+                    );
+                    sleep(delay).await;
+                }
+                None => {
+                    backoff.reset();
+                    sleep(self.poll_interval).await;
+                }
+            }
+        }
+    }
+
+    // Handle method passes a message to the bot and builds its reply, direct messages are answered directly
+    fn handle(&mut self, message: &Message) -> Option<Message> {
+        let text = match Command::parse(&message.message, self.prefix) {
+            Some(command) => self.bot.on_command(&command, message),
+            None => self.bot.on_message(message),
+        }?;
+
+        let mut reply = Message::new(self.bot.name(), &text, &message.room);
+        if message.to.is_some() {
+            reply.to = Some(message.user.clone());
+        }
+        Some(reply)
+    }
+
+    // Send method sends a reply, waiting for the rate limits and for the server to be reachable; rejected replies are dropped
+    async fn send(&self, reply: Message) {
+        let mut backoff = Backoff::default();
+
+        loop {
+            if let Err(wait) = self.limiter.check(()) {
+                sleep(wait).await;
+                continue;
+            }
+
+            match send_message(self.socket_addr, &reply).await {
+                Ok(_) => return,
+                Err(SendError::RateLimited(wait)) => sleep(wait).await,
+                Err(SendError::Unreachable(e)) => {
+                    let delay = backoff.next_delay();
+                    warn!(%e, "failed to send a reply, retrying in {delay:?}");
+                    sleep(delay).await;
+                }
+                Err(e @ SendError::Rejected(_)) => {
+                    warn!(%e, "reply dropped");
+                    return;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,352 @@
+/// Client module: functions used by the clients to talk with the server
+// Necessary imports
+use crate::{
+    export::ExportQuery,
+    messages::Message,
+    moderation::Sanction,
+    request::encode_component,
+    response::{parse_response, Response, Status},
+    validation::ValidationPolicy,
+};
+use anyhow::anyhow;
+use std::{error::Error, fmt, net::SocketAddr, time::Duration};
+use tokio::{
+    io::AsyncWriteExt,
+    net::{lookup_host, TcpStream},
+    time::timeout,
+};
+
+/// Time a request to the server can take, from the connection to the end of the response
+pub static REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Resolve function resolves the hostname (or ip address) and port of the server into a socket address,
+/// when the hostname has several addresses (e.g. localhost is both ::1 and 127.0.0.1) they are tried in turn
+/// and the first one accepting a connection is chosen
+///
+/// Args:
+///     - host: hostname or ip address of the server (IPv6 addresses may be enclosed in brackets)
+///     - port: port of the server
+pub async fn resolve(host: &str, port: u16) -> Result<SocketAddr, anyhow::Error> {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    let addrs: Vec<SocketAddr> = lookup_host((host, port))
+        .await
+        .map_err(|e| anyhow!("Failed to resolve {host}: {e}"))?
+        .collect();
+
+    // If none of them answers (e.g. the server is not running yet) the first one is used
+    let first = *addrs
+        .first()
+        .ok_or(anyhow!("No address found for {host}"))?;
+    if addrs.len() > 1 {
+        for addr in addrs {
+            if let Ok(Ok(_)) = timeout(REQUEST_TIMEOUT, TcpStream::connect(addr)).await {
+                return Ok(addr);
+            }
+        }
+    }
+
+    Ok(first)
+}
+
+/// Send request function connects to the server, sends an HTTP request and returns the parsed response
+///
+/// Args:
+///     - socket_addr: socket address of the server
+///     - method: method of the request (GET or POST)
+///     - uri: uri of the request
+///     - body: json body of the request, if exists
+pub async fn send_request(
+    socket_addr: SocketAddr,
+    method: &str,
+    uri: &str,
+    body: Option<&str>,
+) -> Result<Response, anyhow::Error> {
+    request(socket_addr, method, uri, body, None).await
+}
+
+/// Send admin request function sends an HTTP request to the admin api, authenticated with an admin token
+///
+/// Args:
+///     - socket_addr: socket address of the server
+///     - token: admin token
+///     - method: method of the request (GET, POST or DELETE)
+///     - uri: uri of the request
+///     - body: json body of the request, if exists
+pub async fn send_admin_request(
+    socket_addr: SocketAddr,
+    token: &str,
+    method: &str,
+    uri: &str,
+    body: Option<&str>,
+) -> Result<(), anyhow::Error> {
+    let response = request(socket_addr, method, uri, body, Some(token)).await?;
+
+    if response.status() != Status::Ok {
+        return Err(anyhow!("{}", response.body));
+    }
+
+    Ok(())
+}
+
+// Request function sends an HTTP request, with the bearer token if given, and returns the parsed response,
+// a server that doesn't answer within REQUEST_TIMEOUT is an error so that the callers (the tui) don't hang
+async fn request(
+    socket_addr: SocketAddr,
+    method: &str,
+    uri: &str,
+    body: Option<&str>,
+    token: Option<&str>,
+) -> Result<Response, anyhow::Error> {
+    // Construct the request, the body (if exists) is sent as json
+    let authorization = token
+        .map(|token| format!("Authorization: Bearer {token}\r\n"))
+        .unwrap_or_default();
+    let request = match body {
+        Some(body) => format!(
+            "{method} {uri} HTTP/1.1\r\nHost: {socket_addr}\r\n{authorization}Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        ),
+        None => format!("{method} {uri} HTTP/1.1\r\nHost: {socket_addr}\r\n{authorization}\r\n"),
+    };
+
+    let exchange = async {
+        // Connection to the server
+        let mut stream = TcpStream::connect(socket_addr).await?;
+        stream.write_all(request.as_bytes()).await?;
+
+        parse_response(&mut stream).await
+    };
+
+    timeout(REQUEST_TIMEOUT, exchange)
+        .await
+        .map_err(|_| anyhow!("The server didn't answer within {REQUEST_TIMEOUT:?}"))?
+}
+
+/// Fetch messages function gets the messages of a room visible to a user
+///
+/// Args:
+///     - socket_addr: socket address of the server
+///     - room: room to read
+///     - user: username of the reader
+pub async fn fetch_messages(
+    socket_addr: SocketAddr,
+    room: &str,
+    user: &str,
+) -> Result<Vec<Message>, anyhow::Error> {
+    let uri = format!(
+        "/messages?room={}&user={}",
+        encode_component(room),
+        encode_component(user)
+    );
+    let response = send_request(socket_addr, "GET", &uri, None).await?;
+
+    if response.status() != Status::Ok {
+        return Err(anyhow!("Failed to fetch messages: {}", response.body));
+    }
+
+    Ok(serde_json::from_str::<Vec<Message>>(&response.body)?)
+}
+
+/// SendError enum enumerates the reasons a message was not delivered
+#[derive(Debug)]
+pub enum SendError {
+    /// The server could not be reached, the message can be sent again later
+    Unreachable(anyhow::Error),
+    /// The server refused the message, the reason is sent back by the server
+    Rejected(String),
+    /// The client is sending too fast, the message can be sent again after the delay
+    RateLimited(Duration),
+}
+
+// Display implementation for SendError enum
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::Unreachable(e) => write!(f, "Server unreachable: {e}"),
+            SendError::Rejected(reason) => write!(f, "Message rejected: {reason}"),
+            SendError::RateLimited(retry_after) => {
+                write!(f, "Slow down, retry in {}s", retry_after.as_secs())
+            }
+        }
+    }
+}
+
+// Error implementation for SendError enum
+impl Error for SendError {}
+
+/// Send message function posts a new message to the server and returns the id assigned to it
+///
+/// Args:
+///     - socket_addr: socket address of the server
+///     - message: message to send
+pub async fn send_message(socket_addr: SocketAddr, message: &Message) -> Result<u64, SendError> {
+    let body = serde_json::to_string(message).map_err(|e| SendError::Rejected(e.to_string()))?;
+    let response = send_request(socket_addr, "POST", "/messages", Some(&body))
+        .await
+        .map_err(SendError::Unreachable)?;
+
+    // A server that is temporarily unavailable will accept the message later
+    match response.status() {
+        Status::Ok => {}
+        Status::ServiceUnavailable => {
+            return Err(SendError::Unreachable(anyhow!("{}", response.body)));
+        }
+        Status::TooManyRequests => {
+            let retry_after = response
+                .get_header("Retry-After")
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(1);
+            return Err(SendError::RateLimited(Duration::from_secs(retry_after)));
+        }
+        _ => return Err(SendError::Rejected(response.body)),
+    }
+
+    // Servers not sending back the id are answered with 0
+    let id = serde_json::from_str::<serde_json::Value>(&response.body)
+        .ok()
+        .and_then(|body| body["id"].as_u64())
+        .unwrap_or_default();
+
+    Ok(id)
+}
+
+/// Fetch policy function gets the rules the messages sent to the server must follow
+///
+/// Args:
+///     - socket_addr: socket address of the server
+pub async fn fetch_policy(socket_addr: SocketAddr) -> Result<ValidationPolicy, anyhow::Error> {
+    let response = send_request(socket_addr, "GET", "/policy", None).await?;
+
+    if response.status() != Status::Ok {
+        return Err(anyhow!("Failed to fetch the policy: {}", response.body));
+    }
+
+    Ok(serde_json::from_str::<ValidationPolicy>(&response.body)?)
+}
+
+/// Moderate function mutes, bans, unmutes or unbans a user or an address through the admin api
+///
+/// Args:
+///     - socket_addr: socket address of the server
+///     - token: admin token
+///     - action: "mute", "ban", "unmute" or "unban"
+///     - sanction: who is sanctioned and for how long
+pub async fn moderate(
+    socket_addr: SocketAddr,
+    token: &str,
+    action: &str,
+    sanction: &Sanction,
+) -> Result<(), anyhow::Error> {
+    let body = serde_json::to_string(sanction)?;
+    send_admin_request(
+        socket_addr,
+        token,
+        "POST",
+        &format!("/admin/{action}"),
+        Some(&body),
+    )
+    .await
+}
+
+/// Search messages function searches the messages visible to a user in all the rooms
+///
+/// Args:
+///     - socket_addr: socket address of the server
+///     - text: text to look for (a regular expression if regex is true)
+///     - regex: whether the text is a regular expression
+///     - reader: username of the reader
+pub async fn search_messages(
+    socket_addr: SocketAddr,
+    text: &str,
+    regex: bool,
+    reader: &str,
+) -> Result<Vec<Message>, anyhow::Error> {
+    let uri = format!(
+        "/messages/search?q={}&regex={regex}&reader={}",
+        encode_component(text),
+        encode_component(reader)
+    );
+    let response = send_request(socket_addr, "GET", &uri, None).await?;
+
+    if response.status() != Status::Ok {
+        return Err(anyhow!("{}", response.body));
+    }
+
+    Ok(serde_json::from_str::<Vec<Message>>(&response.body)?)
+}
+
+/// Export messages function downloads a transcript of the history
+///
+/// Args:
+///     - socket_addr: socket address of the server
+///     - query: room, user, time range and format of the transcript
+pub async fn export_messages(
+    socket_addr: SocketAddr,
+    query: &ExportQuery,
+) -> Result<String, anyhow::Error> {
+    let uri = format!("/export?{}", query.query_string());
+    let response = send_request(socket_addr, "GET", &uri, None).await?;
+
+    if response.status() != Status::Ok {
+        return Err(anyhow!("{}", response.body));
+    }
+
+    Ok(response.body)
+}
+
+/// Backoff struct computing the delays between reconnection attempts, doubling at every failure
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    /// Delay after the first failure
+    initial: Duration,
+
+    /// Maximum delay
+    max: Duration,
+
+    /// Number of consecutive failures
+    attempt: u32,
+}
+
+// Methods for the Backoff struct
+impl Backoff {
+    /// Constructor method
+    ///
+    /// Args:
+    ///     - initial: delay after the first failure
+    ///     - max: maximum delay
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            attempt: 0,
+        }
+    }
+
+    /// Next delay method records a failure and returns how long to wait before the next attempt
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self
+            .initial
+            .saturating_mul(2_u32.saturating_pow(self.attempt))
+            .min(self.max);
+        self.attempt = self.attempt.saturating_add(1);
+        delay
+    }
+
+    /// Attempt method returns the number of consecutive failures
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// Reset method records a success, the next failure starts again from the initial delay
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+// Default implementation for Backoff struct: from 500ms up to 30s
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(500), Duration::from_secs(30))
+    }
+}
@@ -0,0 +1,764 @@
+/// Config module
+// Necessary imports
+use crate::{args::Args, validation::ValidationPolicy, IP_ADDR, PORT};
+use anyhow::{anyhow, Context};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use std::{
+    env, fmt, fs,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Prefix of the environment variables overriding the configuration
+pub static ENV_PREFIX: &str = "CHAT_TUI_";
+
+/// ServerConfig struct containing the settings of the server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Ip address the server listens on (IPv4 or IPv6)
+    pub ip: IpAddr,
+
+    /// Port the server listens on
+    pub port: u16,
+
+    /// Whether an IPv6 address also accepts IPv4 connections (listening on "::" serves both)
+    pub dual_stack: bool,
+
+    /// Persistent storage of the messages
+    pub storage: StorageConfig,
+
+    /// Limits applied to the requests
+    pub limits: LimitsConfig,
+
+    /// Graceful shutdown of the server
+    pub shutdown: ShutdownConfig,
+
+    /// Rate limits of the sent messages
+    pub rate_limit: RateLimitConfig,
+
+    /// Rules the sent messages must follow
+    pub validation: ValidationPolicy,
+
+    /// Limits of the history kept by the server
+    pub retention: RetentionConfig,
+
+    /// Admin api
+    pub admin: AdminConfig,
+
+    /// Content filters of the sent messages
+    pub filters: FiltersConfig,
+
+    /// Outgoing webhooks notified of the new messages
+    pub webhooks: Vec<WebhookConfig>,
+
+    /// Incoming webhooks posting the messages of external systems
+    pub incoming_webhooks: IncomingWebhooksConfig,
+
+    /// Logs of the server
+    pub log: LogConfig,
+}
+
+/// StorageConfig struct containing the settings of the persistent storage of the messages
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// File the messages are saved to (JSON Lines), the messages are only kept in memory if None
+    pub path: Option<PathBuf>,
+}
+
+/// LimitsConfig struct containing the limits applied to the requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Maximum size in bytes of the body of a request
+    pub max_body_bytes: usize,
+
+    /// Maximum size in bytes of the request line and the headers together
+    pub max_header_bytes: usize,
+
+    /// Maximum number of headers of a request
+    pub max_headers: usize,
+
+    /// Maximum number of connections handled at the same time, the others are answered with 503
+    pub max_connections: usize,
+
+    /// Seconds a client has to send the request line and the headers
+    pub header_timeout_secs: u64,
+
+    /// Seconds a client has to send the body of the request
+    pub read_timeout_secs: u64,
+
+    /// Seconds a client has to receive the response
+    pub write_timeout_secs: u64,
+}
+
+/// RetentionConfig struct containing the limits of the history kept by the server, 0 disables a limit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetentionConfig {
+    /// Maximum number of messages
+    pub max_messages: usize,
+
+    /// Maximum age in seconds of the messages
+    pub max_age_secs: u64,
+
+    /// Maximum memory in bytes used by the messages (estimated)
+    pub max_bytes: usize,
+
+    /// Seconds between two applications of the limits
+    pub interval_secs: u64,
+
+    /// File the removed messages are appended to (JSON Lines), they are dropped if None
+    pub archive: Option<PathBuf>,
+}
+
+/// AdminConfig struct containing the settings of the admin api
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdminConfig {
+    /// Tokens granting access to the admin api, the api is disabled if empty
+    pub tokens: Vec<String>,
+
+    /// File the mutes and the bans are saved to (JSON), they are only kept in memory (until a restart) if None
+    pub sanctions_path: Option<PathBuf>,
+}
+
+/// FiltersConfig struct containing the settings of the content filters
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FiltersConfig {
+    /// File the filters are loaded from (TOML), reloaded on SIGHUP; no filter is applied if None
+    pub path: Option<PathBuf>,
+}
+
+/// WebhookConfig struct containing the settings of an outgoing webhook, empty filters match every message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebhookConfig {
+    /// Url the messages are posted to (http only)
+    pub url: String,
+
+    /// Secret signing the requests with HMAC-SHA256, they are not signed if None
+    pub secret: Option<String>,
+
+    /// Rooms of the messages sent to the webhook
+    pub rooms: Vec<String>,
+
+    /// Authors of the messages sent to the webhook
+    pub users: Vec<String>,
+
+    /// Words the messages sent to the webhook must contain (case insensitive, any of them)
+    pub keywords: Vec<String>,
+
+    /// Attempts after a failed delivery
+    pub max_retries: u32,
+
+    /// Seconds a delivery attempt can take
+    pub timeout_secs: u64,
+}
+
+/// IncomingWebhooksConfig struct containing the settings of the incoming webhooks
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IncomingWebhooksConfig {
+    /// File the webhooks created through the admin api are saved to (JSON), they are only kept in memory if None
+    pub path: Option<PathBuf>,
+}
+
+/// ShutdownConfig struct containing the settings of the graceful shutdown
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShutdownConfig {
+    /// Seconds the running connections are given to finish before they are aborted
+    pub drain_timeout_secs: u64,
+}
+
+/// RateLimitConfig struct containing the rate limits of the sent messages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// Limit of every user
+    #[serde(deserialize_with = "user_rate")]
+    pub user: RateConfig,
+
+    /// Limit of every client address, shared by all the users connecting from it
+    #[serde(deserialize_with = "ip_rate")]
+    pub ip: RateConfig,
+}
+
+/// RateConfig struct containing a rate limit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateConfig {
+    /// Messages allowed every minute on average, 0 disables the limit
+    pub per_minute: u32,
+
+    /// Messages that can be sent in a burst
+    pub burst: u32,
+}
+
+// PartialRate struct representing a rate limit table of the config file, where the fields can be missing
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PartialRate {
+    per_minute: Option<u32>,
+    burst: Option<u32>,
+}
+
+/// ClientConfig struct containing the settings of the tui client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientConfig {
+    /// Hostname or ip address of the server
+    pub host: String,
+
+    /// Port of the server
+    pub port: u16,
+
+    /// Username used to log in, the client asks for it if None
+    pub username: Option<String>,
+
+    /// How message timestamps are displayed
+    pub timestamps: TimestampFormat,
+
+    /// File where the sent lines are saved to be recalled in the next sessions
+    pub history_file: Option<PathBuf>,
+
+    /// Whether new mentions ring the terminal bell
+    pub bell: bool,
+
+    /// Token of the admin api, it enables the admin commands
+    pub admin_token: Option<String>,
+
+    /// Colors of the tui
+    pub theme: Theme,
+
+    /// Key bindings of the tui
+    pub keybindings: KeyBindings,
+
+    /// Logs of the client, written only to a file so that they don't mix with the tui
+    pub log: LogConfig,
+}
+
+/// LogConfig struct containing the settings of the logs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// Level of the logs or filter directives like "info,chat_lib=debug" (RUST_LOG overrides it)
+    pub level: String,
+
+    /// Format of the logs
+    pub format: LogFormat,
+
+    /// File the logs are appended to, the server logs to stdout and the client doesn't log if None
+    pub file: Option<PathBuf>,
+}
+
+/// LogFormat enum enumerates the formats of the logs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable lines
+    #[default]
+    Pretty,
+    /// A JSON object per line
+    Json,
+}
+
+/// TimestampFormat enum enumerates the ways a message timestamp can be displayed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampFormat {
+    /// Elapsed time since the message was sent (e.g. 5m)
+    #[default]
+    Relative,
+    /// Local time of the message (e.g. 14:03)
+    Absolute,
+    /// Timestamps are hidden
+    None,
+}
+
+/// Theme struct containing the colors of the tui (color names like "blue", "darkgray" or "#rrggbb")
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    /// Color of the borders and titles
+    pub border: String,
+
+    /// Background of the messages sent by the local user
+    pub own_message: String,
+
+    /// Background of the messages mentioning the local user
+    pub mention: String,
+
+    /// Color of the status line
+    pub status: String,
+
+    /// Colors assigned to the usernames
+    pub user_colors: Vec<String>,
+}
+
+/// KeyBindings struct containing the keys of the tui actions (e.g. "ctrl-f", "tab", "f2")
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyBindings {
+    /// Quit the client
+    pub quit: String,
+
+    /// Reverse search in the input history
+    pub history_search: String,
+
+    /// Search through the chat history
+    pub message_search: String,
+
+    /// Complete the command, argument or username being typed
+    pub complete: String,
+
+    /// Send again the messages that were not delivered
+    pub retry: String,
+
+    /// Drop the newest message that was not delivered
+    pub discard: String,
+}
+
+// Default implementation for ServerConfig struct
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            ip: IP_ADDR,
+            port: PORT,
+            dual_stack: true,
+            storage: StorageConfig::default(),
+            limits: LimitsConfig::default(),
+            shutdown: ShutdownConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            validation: ValidationPolicy::default(),
+            retention: RetentionConfig::default(),
+            admin: AdminConfig::default(),
+            filters: FiltersConfig::default(),
+            webhooks: Vec::new(),
+            incoming_webhooks: IncomingWebhooksConfig::default(),
+            log: LogConfig::default(),
+        }
+    }
+}
+
+// Default implementation for WebhookConfig struct
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            secret: None,
+            rooms: Vec::new(),
+            users: Vec::new(),
+            keywords: Vec::new(),
+            max_retries: 3,
+            timeout_secs: 5,
+        }
+    }
+}
+
+// Default implementation for LimitsConfig struct
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: 64 * 1024,
+            max_header_bytes: 8 * 1024,
+            max_headers: 64,
+            max_connections: 1024,
+            header_timeout_secs: 5,
+            read_timeout_secs: 10,
+            write_timeout_secs: 10,
+        }
+    }
+}
+
+// Default implementation for RetentionConfig struct
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            max_messages: 100_000,
+            max_age_secs: 0,
+            max_bytes: 0,
+            interval_secs: 60,
+            archive: None,
+        }
+    }
+}
+
+// Default implementation for ShutdownConfig struct
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            drain_timeout_secs: 10,
+        }
+    }
+}
+
+// Default implementation for RateLimitConfig struct
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            user: RateConfig {
+                per_minute: 30,
+                burst: 10,
+            },
+            ip: RateConfig {
+                per_minute: 60,
+                burst: 20,
+            },
+        }
+    }
+}
+
+// Default implementation for RateConfig struct: no limit
+impl Default for RateConfig {
+    fn default() -> Self {
+        Self {
+            per_minute: 0,
+            burst: 1,
+        }
+    }
+}
+
+// Methods for the PartialRate struct
+impl PartialRate {
+    // Or method fills the missing fields with the ones of the default limit
+    fn or(self, default: RateConfig) -> RateConfig {
+        RateConfig {
+            per_minute: self.per_minute.unwrap_or(default.per_minute),
+            burst: self.burst.unwrap_or(default.burst),
+        }
+    }
+}
+
+// Default implementation for ClientConfig struct
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            host: IP_ADDR.to_string(),
+            port: PORT,
+            username: None,
+            timestamps: TimestampFormat::default(),
+            history_file: None,
+            bell: false,
+            admin_token: None,
+            theme: Theme::default(),
+            keybindings: KeyBindings::default(),
+            log: LogConfig::default(),
+        }
+    }
+}
+
+// Default implementation for LogConfig struct
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            format: LogFormat::default(),
+            file: None,
+        }
+    }
+}
+
+// Default implementation for Theme struct
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            border: "blue".to_string(),
+            own_message: "darkgray".to_string(),
+            mention: "red".to_string(),
+            status: "yellow".to_string(),
+            user_colors: [
+                "cyan",
+                "green",
+                "yellow",
+                "magenta",
+                "lightred",
+                "lightblue",
+                "lightgreen",
+                "lightmagenta",
+            ]
+            .map(Into::into)
+            .to_vec(),
+        }
+    }
+}
+
+// Default implementation for KeyBindings struct
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            quit: "ctrl-c".to_string(),
+            history_search: "ctrl-r".to_string(),
+            message_search: "ctrl-f".to_string(),
+            complete: "tab".to_string(),
+            retry: "ctrl-t".to_string(),
+            discard: "ctrl-d".to_string(),
+        }
+    }
+}
+
+// FromStr implementation for TimestampFormat enum: parses the value of the --timestamps flag
+impl FromStr for TimestampFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "relative" => Ok(TimestampFormat::Relative),
+            "absolute" => Ok(TimestampFormat::Absolute),
+            "none" => Ok(TimestampFormat::None),
+            f => Err(anyhow!("unsupported timestamp format: {f}")),
+        }
+    }
+}
+
+// FromStr implementation for LogFormat enum: used to read the format from the environment
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            f => Err(anyhow!("unsupported log format: {f}")),
+        }
+    }
+}
+
+// Methods for the LogConfig struct
+impl LogConfig {
+    // Apply env method applies the CHAT_TUI_LOG_* environment variables
+    fn apply_env(&mut self) -> Result<(), anyhow::Error> {
+        env_override("LOG_LEVEL", &mut self.level)?;
+        env_override("LOG_FORMAT", &mut self.format)?;
+        if let Some(path) = env_var("LOG_FILE") {
+            self.file = Some(path.into());
+        }
+
+        Ok(())
+    }
+}
+
+// Methods for the ServerConfig struct
+impl ServerConfig {
+    /// Load method builds the configuration from the defaults, the config file (server.toml),
+    /// the CHAT_TUI_* environment variables and the cli arguments, each layer overriding the previous one
+    ///
+    /// Args:
+    ///     - args: cli arguments
+    pub fn load(args: &Args) -> Result<Self, anyhow::Error> {
+        let mut config: ServerConfig =
+            load_file(args.config.as_deref(), "SERVER_CONFIG", "server.toml")?;
+
+        // Environment variables
+        env_override("IP", &mut config.ip)?;
+        env_override("PORT", &mut config.port)?;
+        env_override("DUAL_STACK", &mut config.dual_stack)?;
+        env_override("MAX_BODY_BYTES", &mut config.limits.max_body_bytes)?;
+        env_override("MAX_HEADER_BYTES", &mut config.limits.max_header_bytes)?;
+        env_override("MAX_HEADERS", &mut config.limits.max_headers)?;
+        env_override("MAX_CONNECTIONS", &mut config.limits.max_connections)?;
+        env_override(
+            "HEADER_TIMEOUT_SECS",
+            &mut config.limits.header_timeout_secs,
+        )?;
+        env_override("READ_TIMEOUT_SECS", &mut config.limits.read_timeout_secs)?;
+        env_override("WRITE_TIMEOUT_SECS", &mut config.limits.write_timeout_secs)?;
+        env_override(
+            "USER_RATE_PER_MINUTE",
+            &mut config.rate_limit.user.per_minute,
+        )?;
+        env_override("USER_RATE_BURST", &mut config.rate_limit.user.burst)?;
+        env_override("IP_RATE_PER_MINUTE", &mut config.rate_limit.ip.per_minute)?;
+        env_override("IP_RATE_BURST", &mut config.rate_limit.ip.burst)?;
+        env_override("MAX_MESSAGE_LEN", &mut config.validation.max_message_len)?;
+        env_override("MAX_NAME_LEN", &mut config.validation.max_name_len)?;
+        env_override("MAX_MESSAGES", &mut config.retention.max_messages)?;
+        env_override("MAX_AGE_SECS", &mut config.retention.max_age_secs)?;
+        env_override("MAX_BYTES", &mut config.retention.max_bytes)?;
+        env_override(
+            "RETENTION_INTERVAL_SECS",
+            &mut config.retention.interval_secs,
+        )?;
+        if let Some(path) = env_var("ARCHIVE_PATH") {
+            config.retention.archive = Some(path.into());
+        }
+        if let Some(tokens) = env_var("ADMIN_TOKENS") {
+            config.admin.tokens = tokens.split(',').map(|token| token.trim().into()).collect();
+        }
+        if let Some(path) = env_var("FILTERS_PATH") {
+            config.filters.path = Some(path.into());
+        }
+        if let Some(path) = env_var("SANCTIONS_PATH") {
+            config.admin.sanctions_path = Some(path.into());
+        }
+        if let Some(path) = env_var("INCOMING_WEBHOOKS_PATH") {
+            config.incoming_webhooks.path = Some(path.into());
+        }
+        config.log.apply_env()?;
+        env_override(
+            "DRAIN_TIMEOUT_SECS",
+            &mut config.shutdown.drain_timeout_secs,
+        )?;
+        if let Some(path) = env_var("STORAGE_PATH") {
+            config.storage.path = Some(path.into());
+        }
+
+        // Cli arguments
+        if let Some(ip) = args.ip {
+            config.ip = ip;
+        }
+        if let Some(port) = args.port {
+            config.port = port;
+        }
+
+        Ok(config)
+    }
+
+    /// Socket addr method returns the socket address the server listens on
+    pub fn socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.ip, self.port)
+    }
+}
+
+// Methods for the ClientConfig struct
+impl ClientConfig {
+    /// Load method builds the configuration from the defaults, the config file (client.toml),
+    /// the CHAT_TUI_* environment variables and the shared cli arguments, each layer overriding the previous one
+    ///
+    /// Args:
+    ///     - args: cli arguments
+    pub fn load(args: &Args) -> Result<Self, anyhow::Error> {
+        let mut config: ClientConfig =
+            load_file(args.config.as_deref(), "CLIENT_CONFIG", "client.toml")?;
+
+        // Environment variables (CHAT_TUI_HOST wins over CHAT_TUI_IP)
+        if let Some(ip) = env_var("IP") {
+            config.host = ip
+                .parse::<IpAddr>()
+                .map_err(|e| anyhow!("Invalid value of {ENV_PREFIX}IP: {e}"))?
+                .to_string();
+        }
+        env_override("HOST", &mut config.host)?;
+        env_override("PORT", &mut config.port)?;
+        env_override("TIMESTAMPS", &mut config.timestamps)?;
+        env_override("BELL", &mut config.bell)?;
+        if let Some(username) = env_var("USERNAME") {
+            config.username = Some(username);
+        }
+        if let Some(path) = env_var("HISTORY_FILE") {
+            config.history_file = Some(path.into());
+        }
+        if let Some(token) = env_var("ADMIN_TOKEN") {
+            config.admin_token = Some(token);
+        }
+        config.log.apply_env()?;
+
+        // Cli arguments
+        if let Some(ip) = args.ip {
+            config.host = ip.to_string();
+        }
+        if let Some(port) = args.port {
+            config.port = port;
+        }
+
+        Ok(config)
+    }
+}
+
+/// Config dir function returns the directory of the default config files ($XDG_CONFIG_HOME/chat-tui or ~/.config/chat-tui)
+pub fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .map(|dir| dir.join("chat-tui"))
+}
+
+// Load file function reads a TOML config file: the one passed with --config or with the environment variable
+// of the binary (CHAT_TUI_SERVER_CONFIG or CHAT_TUI_CLIENT_CONFIG, each file only fits its own binary) must exist,
+// the default one is optional and the default configuration is used without it
+fn load_file<T: DeserializeOwned + Default>(
+    path: Option<&Path>,
+    env_name: &str,
+    default_name: &str,
+) -> Result<T, anyhow::Error> {
+    let (path, required) = match path
+        .map(PathBuf::from)
+        .or(env_var(env_name).map(Into::into))
+    {
+        Some(path) => (path, true),
+        None => match config_dir() {
+            Some(dir) => (dir.join(default_name), false),
+            None => return Ok(T::default()),
+        },
+    };
+
+    if !required && !path.exists() {
+        return Ok(T::default());
+    }
+
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read config file {}", path.display()))?;
+    toml::from_str(&content).with_context(|| format!("Invalid config file {}", path.display()))
+}
+
+// User rate function reads the [rate_limit.user] table, the missing fields keep the defaults of the user limit
+fn user_rate<'de, D: Deserializer<'de>>(deserializer: D) -> Result<RateConfig, D::Error> {
+    PartialRate::deserialize(deserializer).map(|rate| rate.or(RateLimitConfig::default().user))
+}
+
+// Ip rate function reads the [rate_limit.ip] table, the missing fields keep the defaults of the ip limit
+fn ip_rate<'de, D: Deserializer<'de>>(deserializer: D) -> Result<RateConfig, D::Error> {
+    PartialRate::deserialize(deserializer).map(|rate| rate.or(RateLimitConfig::default().ip))
+}
+
+// Env var function reads a CHAT_TUI_* environment variable
+fn env_var(name: &str) -> Option<String> {
+    env::var(format!("{ENV_PREFIX}{name}")).ok()
+}
+
+// Env override function replaces a setting with the value of a CHAT_TUI_* environment variable, if set
+fn env_override<T>(name: &str, setting: &mut T) -> Result<(), anyhow::Error>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    if let Some(value) = env_var(name) {
+        *setting = value
+            .parse()
+            .map_err(|e| anyhow!("Invalid value of {ENV_PREFIX}{name}: {e}"))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_rate_limit_tables_keep_the_defaults() {
+        let config: ServerConfig = toml::from_str("[rate_limit.user]\nper_minute = 5\n").unwrap();
+        assert_eq!(
+            (
+                config.rate_limit.user.per_minute,
+                config.rate_limit.user.burst
+            ),
+            (5, 10)
+        );
+        assert_eq!(
+            (config.rate_limit.ip.per_minute, config.rate_limit.ip.burst),
+            (60, 20)
+        );
+
+        let config: ServerConfig = toml::from_str("[rate_limit.ip]\nburst = 3\n").unwrap();
+        assert_eq!(
+            (config.rate_limit.ip.per_minute, config.rate_limit.ip.burst),
+            (60, 3)
+        );
+        assert!(toml::from_str::<ServerConfig>("[rate_limit.ip]\nrate = 3\n").is_err());
+    }
+}
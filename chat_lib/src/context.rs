@@ -0,0 +1,85 @@
+/// Context module
+// Necessary imports
+use crate::{
+    config::ServerConfig,
+    filter::FilterPipeline,
+    hooks::HookRegistry,
+    incoming::IncomingWebhooks,
+    messages::{unix_now, Message, State},
+    metrics::Metrics,
+    moderation::Moderation,
+    rate_limit::RateLimits,
+    shutdown::Shutdown,
+    storage::Store,
+};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+/// Context struct containing everything the server needs to handle the connections
+#[derive(Debug)]
+pub struct Context {
+    /// State of the server with all the messages
+    pub state: State,
+
+    /// Configuration of the server
+    pub config: ServerConfig,
+
+    /// Persistent storage of the messages, if configured
+    pub store: Option<Store>,
+
+    /// Shutdown state of the server
+    pub shutdown: Shutdown,
+
+    /// Rate limiters of the sent messages
+    pub rate_limits: RateLimits,
+
+    /// Mutes and bans decided by the admins
+    pub moderation: Moderation,
+
+    /// Content filters applied to the sent messages (registered among the hooks), kept to reload them
+    pub filters: Arc<FilterPipeline>,
+
+    /// Hooks called while the messages are received, stored and delivered
+    pub hooks: HookRegistry,
+
+    /// Incoming webhooks created by the admins
+    pub incoming: IncomingWebhooks,
+
+    /// Counters exposed on /metrics
+    pub metrics: Metrics,
+
+    /// Id of the last message, kept apart from the state because the retention may remove every message
+    pub last_id: AtomicU64,
+}
+
+// Methods for the Context struct
+impl Context {
+    /// Next id method returns the id of a new message
+    pub fn next_id(&self) -> u64 {
+        self.last_id.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Publish method stamps a new message with an id and the server time, saves it and adds it to the state,
+    /// then the hooks are notified. Returns the id of the message
+    ///
+    /// Args:
+    ///     - message: message to publish, already checked
+    pub async fn publish(&self, mut message: Message) -> Result<u64, anyhow::Error> {
+        let mut msgs = self.state.lock().await;
+        message.id = self.next_id();
+        message.timestamp = unix_now();
+
+        // The message is saved while the state is locked so that the store keeps the same order
+        if let Some(store) = &self.store {
+            store.append(&message).await?;
+        }
+        let id = message.id;
+        msgs.push(message);
+        self.metrics.message_published();
+        self.hooks.on_store(&msgs[msgs.len() - 1]);
+
+        Ok(id)
+    }
+}
@@ -0,0 +1,381 @@
+/// Export module: transcripts of the chat history in several formats
+// Necessary imports
+use crate::messages::{Message, MessageKind};
+use anyhow::anyhow;
+use std::{collections::HashMap, fmt::Write as _, path::Path, str::FromStr};
+
+/// ExportFormat enum enumerates the formats of a transcript
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A JSON message per line, the same format of the store (it can be imported back)
+    #[default]
+    Jsonl,
+    /// Plain text, a line per message
+    Text,
+    /// Markdown list
+    Markdown,
+    /// Standalone HTML page
+    Html,
+}
+
+/// ExportQuery struct representing the parameters of an export
+#[derive(Debug, Default)]
+pub struct ExportQuery {
+    /// Format of the transcript
+    pub format: ExportFormat,
+
+    /// Only the messages of this room are exported, if given
+    pub room: Option<String>,
+
+    /// Only the messages sent by this user are exported, if given
+    pub user: Option<String>,
+
+    /// Username of the reader, direct messages are exported only if they are addressed to or sent by them
+    pub reader: Option<String>,
+
+    /// Only the messages sent at or after this unix timestamp are exported, if given
+    pub since: Option<u64>,
+
+    /// Only the messages sent before this unix timestamp are exported, if given
+    pub until: Option<u64>,
+}
+
+// Methods for the ExportFormat enum
+impl ExportFormat {
+    /// From path method guesses the format from the extension of a file
+    ///
+    /// Args:
+    ///     - path: file the transcript is written to
+    pub fn from_path(path: &Path) -> Result<Self, anyhow::Error> {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .ok_or_else(|| anyhow!("missing file extension (.jsonl, .txt, .md or .html)"))?
+            .parse()
+    }
+
+    /// Content type method returns the MIME type of the format
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Jsonl => "application/jsonl",
+            Self::Text => "text/plain; charset=utf-8",
+            Self::Markdown => "text/markdown; charset=utf-8",
+            Self::Html => "text/html; charset=utf-8",
+        }
+    }
+
+    /// Extension method returns the usual file extension of the format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Jsonl => "jsonl",
+            Self::Text => "txt",
+            Self::Markdown => "md",
+            Self::Html => "html",
+        }
+    }
+}
+
+// FromStr implementation for ExportFormat enum: accepts the names and the extensions of the formats
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "jsonl" | "json" => Ok(Self::Jsonl),
+            "text" | "txt" => Ok(Self::Text),
+            "markdown" | "md" => Ok(Self::Markdown),
+            "html" | "htm" => Ok(Self::Html),
+            f => Err(anyhow!("unsupported export format: {f}")),
+        }
+    }
+}
+
+// TryFrom<&HashMap<String, String>> implementation for ExportQuery struct: constructs the query from the query string parameters
+impl TryFrom<&HashMap<String, String>> for ExportQuery {
+    type Error = anyhow::Error;
+
+    fn try_from(params: &HashMap<String, String>) -> Result<Self, Self::Error> {
+        let timestamp = |name: &str| {
+            params
+                .get(name)
+                .map(|value| {
+                    value
+                        .parse::<u64>()
+                        .map_err(|_| anyhow!("Invalid {name} timestamp: {value}"))
+                })
+                .transpose()
+        };
+
+        Ok(ExportQuery {
+            format: params
+                .get("format")
+                .map(|format| format.parse())
+                .transpose()?
+                .unwrap_or_default(),
+            room: params.get("room").cloned(),
+            user: params.get("user").cloned(),
+            reader: params.get("reader").cloned(),
+            since: timestamp("since")?,
+            until: timestamp("until")?,
+        })
+    }
+}
+
+// Methods for the ExportQuery struct
+impl ExportQuery {
+    /// Query string method encodes the query for the /export endpoint
+    pub fn query_string(&self) -> String {
+        let mut params = vec![format!("format={}", self.format.extension())];
+        let strings = [
+            ("room", &self.room),
+            ("user", &self.user),
+            ("reader", &self.reader),
+        ];
+        for (name, value) in strings {
+            if let Some(value) = value {
+                params.push(format!(
+                    "{name}={}",
+                    crate::request::encode_component(value)
+                ));
+            }
+        }
+        for (name, value) in [("since", self.since), ("until", self.until)] {
+            if let Some(value) = value {
+                params.push(format!("{name}={value}"));
+            }
+        }
+
+        params.join("&")
+    }
+
+    /// Matches method checks if a message belongs to the export
+    ///
+    /// Args:
+    ///     - message: message to check
+    pub fn matches(&self, message: &Message) -> bool {
+        self.room.as_ref().is_none_or(|room| &message.room == room)
+            && self.user.as_ref().is_none_or(|user| &message.user == user)
+            && self.since.is_none_or(|since| message.timestamp >= since)
+            && self.until.is_none_or(|until| message.timestamp < until)
+            && match &self.reader {
+                Some(reader) => message.is_visible_to(reader),
+                None => message.to.is_none(),
+            }
+    }
+}
+
+/// Export function writes the messages matching the query as a transcript
+///
+/// Args:
+///     - messages: messages to export, from the oldest to the newest
+///     - query: parameters of the export
+pub fn export<'a>(
+    messages: impl IntoIterator<Item = &'a Message>,
+    query: &ExportQuery,
+) -> Result<String, anyhow::Error> {
+    let messages = messages.into_iter().filter(|msg| query.matches(msg));
+    let mut out = String::new();
+
+    match query.format {
+        ExportFormat::Jsonl => {
+            for msg in messages {
+                out.push_str(&serde_json::to_string(msg)?);
+                out.push('\n');
+            }
+        }
+        ExportFormat::Text => {
+            for msg in messages {
+                let _ = writeln!(
+                    out,
+                    "[{}] #{} {}",
+                    format_timestamp(msg.timestamp),
+                    msg.room,
+                    line(msg, |text| single_line(text, " "))
+                );
+            }
+        }
+        ExportFormat::Markdown => {
+            let _ = writeln!(out, "# {}\n", escape_markdown(&title(query)));
+            for msg in messages {
+                let _ = writeln!(
+                    out,
+                    "- `{}` #{} {}",
+                    format_timestamp(msg.timestamp),
+                    escape_markdown(&msg.room),
+                    line(msg, escape_markdown)
+                );
+            }
+        }
+        ExportFormat::Html => {
+            let title = escape_html(&title(query));
+            let _ = writeln!(
+                out,
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n</head>\n<body>\n<h1>{title}</h1>\n<ul>"
+            );
+            for msg in messages {
+                let _ = writeln!(
+                    out,
+                    "<li><time datetime=\"{time}\">{time}</time> #{} {}</li>",
+                    escape_html(&msg.room),
+                    line(msg, escape_html),
+                    time = format_timestamp(msg.timestamp),
+                );
+            }
+            out.push_str("</ul>\n</body>\n</html>\n");
+        }
+    }
+
+    Ok(out)
+}
+
+/// Format timestamp function formats a unix timestamp as a UTC date and time (YYYY-MM-DD HH:MM:SS)
+///
+/// Args:
+///     - timestamp: unix timestamp in seconds
+pub fn format_timestamp(timestamp: u64) -> String {
+    let (days, secs) = (timestamp / 86_400, timestamp % 86_400);
+
+    // Civil date from the days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+// Line function formats the author and the text of a message, escaping the user content with the given function
+fn line(msg: &Message, escape: impl Fn(&str) -> String) -> String {
+    let author = match &msg.to {
+        Some(to) => format!("{} → {}", escape(&msg.user), escape(to)),
+        None => escape(&msg.user),
+    };
+
+    match msg.kind {
+        MessageKind::Text => format!("{author}: {}", escape(&msg.message)),
+        MessageKind::Action => format!("* {author} {}", escape(&msg.message)),
+    }
+}
+
+// Title function describes the export in the header of the Markdown and HTML transcripts
+fn title(query: &ExportQuery) -> String {
+    let mut title = "Transcript".to_string();
+    if let Some(room) = &query.room {
+        let _ = write!(title, " of #{room}");
+    }
+    if let Some(user) = &query.user {
+        let _ = write!(title, " by {user}");
+    }
+    if let Some(since) = query.since {
+        let _ = write!(title, " from {}", format_timestamp(since));
+    }
+    if let Some(until) = query.until {
+        let _ = write!(title, " until {}", format_timestamp(until));
+    }
+    title
+}
+
+// Single line function joins the lines of a text with a separator, so that every message stays on its own line
+fn single_line(text: &str, separator: &str) -> String {
+    text.replace("\r\n", "\n")
+        .split(['\r', '\n'])
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+// Escape markdown function escapes the characters with a meaning in Markdown, the line breaks become <br>
+// so that the text can't leave its list item (and start a heading or a block)
+fn escape_markdown(text: &str) -> String {
+    let escaped = text.chars().fold(String::new(), |mut out, c| {
+        if "\\`*_{}[]<>()#+-.!|~".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+        out
+    });
+    single_line(&escaped, "<br>")
+}
+
+// Escape html function escapes the characters with a meaning in HTML, the line breaks become <br>
+fn escape_html(text: &str) -> String {
+    let escaped = text.chars().fold(String::new(), |mut out, c| {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+        out
+    });
+    single_line(&escaped, "<br>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_timestamp_of_known_dates() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00:00");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14 22:13:20");
+        assert_eq!(format_timestamp(4_107_542_399), "2100-02-28 23:59:59");
+    }
+
+    #[test]
+    fn escape_html_of_special_characters_and_line_breaks() {
+        assert_eq!(
+            escape_html("<a href=\"x\">Tom & Jerry's</a>"),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"
+        );
+        assert_eq!(escape_html("one\ntwo\r\nthree"), "one<br>two<br>three");
+    }
+
+    #[test]
+    fn escape_markdown_of_special_characters_and_line_breaks() {
+        assert_eq!(
+            escape_markdown("*bold* [link](x)"),
+            "\\*bold\\* \\[link\\]\\(x\\)"
+        );
+        assert_eq!(
+            escape_markdown("a\n# title\r- item"),
+            "a<br>\\# title<br>\\- item"
+        );
+    }
+
+    #[test]
+    fn export_keeps_a_line_per_message() {
+        let mut message = Message::new("alice", "first\nsecond", "general");
+        message.timestamp = 1_700_000_000;
+        let messages = [message];
+
+        let query = ExportQuery {
+            format: ExportFormat::Text,
+            ..Default::default()
+        };
+        assert_eq!(
+            export(&messages, &query).unwrap(),
+            "[2023-11-14 22:13:20] #general alice: first second\n"
+        );
+
+        let query = ExportQuery {
+            format: ExportFormat::Markdown,
+            ..Default::default()
+        };
+        assert_eq!(
+            export(&messages, &query).unwrap(),
+            "# Transcript\n\n- `2023-11-14 22:13:20` #general alice: first<br>second\n"
+        );
+    }
+}
@@ -0,0 +1,377 @@
+/// Filter module: content filters applied to the messages before they are stored
+// Necessary imports
+use crate::{hooks::MessageHook, messages::Message};
+use anyhow::{anyhow, Context as _};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Debug,
+    fs,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+use tracing::{info, warn};
+
+/// MessageFilter trait implemented by the filters of the pipeline: a filter can change the message or reject it
+pub trait MessageFilter: Debug + Send + Sync {
+    /// Apply method filters a message, the error is the reason of the rejection
+    ///
+    /// Args:
+    ///     - message: message to filter
+    fn apply(&self, message: &mut Message) -> Result<(), anyhow::Error>;
+}
+
+/// FilterRules struct containing the rules of the filters file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilterRules {
+    /// Words masked in the messages (case insensitive, whole words)
+    pub blocklist: Vec<String>,
+
+    /// Character used to mask the blocked words
+    pub mask: Option<char>,
+
+    /// Regular expressions rejecting the messages they match
+    pub reject_patterns: Vec<String>,
+
+    /// Whether the messages containing links are rejected
+    pub block_links: bool,
+
+    /// Domains (and their subdomains) the links can point to even if links are blocked
+    pub allowed_domains: Vec<String>,
+}
+
+/// Blocklist struct representing the filter masking the blocked words
+#[derive(Debug)]
+pub struct Blocklist {
+    /// Regular expression matching the blocked words
+    regex: Regex,
+
+    /// Character replacing every character of a blocked word
+    mask: char,
+}
+
+/// RegexReject struct representing the filter rejecting the messages matching a regular expression
+#[derive(Debug)]
+pub struct RegexReject {
+    /// Regular expressions rejecting the messages
+    patterns: Vec<Regex>,
+}
+
+/// LinkBlocker struct representing the filter rejecting the messages with links
+#[derive(Debug)]
+pub struct LinkBlocker {
+    /// Regular expression matching the links, the host is captured
+    regex: Regex,
+
+    /// Domains the links can point to
+    allowed_domains: Vec<String>,
+}
+
+/// FilterPipeline struct containing the filters applied to every message, in order
+#[derive(Debug, Default)]
+pub struct FilterPipeline {
+    /// File the filters are loaded from, if any
+    path: Option<PathBuf>,
+
+    /// Filters of the pipeline
+    filters: RwLock<Vec<Box<dyn MessageFilter>>>,
+}
+
+// Methods for the Blocklist struct
+impl Blocklist {
+    /// Constructor method
+    ///
+    /// Args:
+    ///     - words: words to mask
+    ///     - mask: character replacing every character of a blocked word
+    pub fn new(words: &[String], mask: char) -> Result<Self, anyhow::Error> {
+        // A word is only bounded on the sides where it starts or ends with a word character,
+        // otherwise the words like "c++" or "@ss" could never match
+        let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+        let words: Vec<String> = words
+            .iter()
+            .filter(|word| !word.is_empty())
+            .map(|word| {
+                let start = if is_word(word.chars().next()) {
+                    r"\b"
+                } else {
+                    ""
+                };
+                let end = if is_word(word.chars().last()) {
+                    r"\b"
+                } else {
+                    ""
+                };
+                format!("{start}{}{end}", regex::escape(word))
+            })
+            .collect();
+        let regex = RegexBuilder::new(&format!("(?:{})", words.join("|")))
+            .case_insensitive(true)
+            .build()?;
+
+        Ok(Self { regex, mask })
+    }
+}
+
+// MessageFilter implementation for Blocklist struct: the blocked words are masked
+impl MessageFilter for Blocklist {
+    fn apply(&self, message: &mut Message) -> Result<(), anyhow::Error> {
+        if self.regex.is_match(&message.message) {
+            message.message = self
+                .regex
+                .replace_all(&message.message, |caps: &regex::Captures| {
+                    self.mask.to_string().repeat(caps[0].chars().count())
+                })
+                .into_owned();
+        }
+
+        Ok(())
+    }
+}
+
+// MessageFilter implementation for RegexReject struct: the matching messages are rejected
+impl MessageFilter for RegexReject {
+    fn apply(&self, message: &mut Message) -> Result<(), anyhow::Error> {
+        match self
+            .patterns
+            .iter()
+            .any(|pattern| pattern.is_match(&message.message))
+        {
+            true => Err(anyhow!("Message rejected by the content policy")),
+            false => Ok(()),
+        }
+    }
+}
+
+// Methods for the LinkBlocker struct
+impl LinkBlocker {
+    /// Constructor method
+    ///
+    /// Args:
+    ///     - allowed_domains: domains the links can point to
+    pub fn new(allowed_domains: &[String]) -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            regex: Regex::new(r"(?i)\b(?:[a-z][a-z0-9+.-]*://|www\.)([^\s/?#:]+)")?,
+            allowed_domains: allowed_domains
+                .iter()
+                .map(|domain| domain.to_lowercase())
+                .collect(),
+        })
+    }
+
+    // Is allowed method checks if a host belongs to one of the allowed domains
+    fn is_allowed(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        let host = host.strip_prefix("www.").unwrap_or(&host);
+        self.allowed_domains
+            .iter()
+            .any(|domain| host == domain || host.ends_with(&format!(".{domain}")))
+    }
+}
+
+// MessageFilter implementation for LinkBlocker struct: the messages linking outside the allowed domains are rejected
+impl MessageFilter for LinkBlocker {
+    fn apply(&self, message: &mut Message) -> Result<(), anyhow::Error> {
+        match self
+            .regex
+            .captures_iter(&message.message)
+            .any(|caps| !self.is_allowed(&caps[1]))
+        {
+            true => Err(anyhow!("Links are not allowed")),
+            false => Ok(()),
+        }
+    }
+}
+
+// TryFrom<&FilterRules> implementation for the filters: builds the filters enabled by the rules
+impl TryFrom<&FilterRules> for Vec<Box<dyn MessageFilter>> {
+    type Error = anyhow::Error;
+
+    fn try_from(rules: &FilterRules) -> Result<Self, Self::Error> {
+        let mut filters: Vec<Box<dyn MessageFilter>> = Vec::new();
+
+        if !rules.reject_patterns.is_empty() {
+            let patterns = rules
+                .reject_patterns
+                .iter()
+                .map(|pattern| {
+                    Regex::new(pattern).with_context(|| format!("Invalid pattern {pattern:?}"))
+                })
+                .collect::<Result<_, _>>()?;
+            filters.push(Box::new(RegexReject { patterns }));
+        }
+        if rules.block_links {
+            filters.push(Box::new(LinkBlocker::new(&rules.allowed_domains)?));
+        }
+        if !rules.blocklist.is_empty() {
+            filters.push(Box::new(Blocklist::new(
+                &rules.blocklist,
+                rules.mask.unwrap_or('*'),
+            )?));
+        }
+
+        Ok(filters)
+    }
+}
+
+// Methods for the FilterPipeline struct
+impl FilterPipeline {
+    /// Load method builds the pipeline from the filters file, the pipeline is empty without a file
+    ///
+    /// Args:
+    ///     - path: file of the filters (TOML)
+    pub fn load(path: Option<&Path>) -> Result<Self, anyhow::Error> {
+        let pipeline = Self {
+            path: path.map(Path::to_path_buf),
+            ..Default::default()
+        };
+        pipeline.reload()?;
+
+        Ok(pipeline)
+    }
+
+    /// Reload method reads the filters file again, the current filters are kept if it is invalid.
+    /// Returns the number of active filters
+    pub fn reload(&self) -> Result<usize, anyhow::Error> {
+        let Some(path) = &self.path else {
+            return Ok(0);
+        };
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read filters file {}", path.display()))?;
+        let rules: FilterRules = toml::from_str(&content)
+            .with_context(|| format!("Invalid filters file {}", path.display()))?;
+        let filters = Vec::<Box<dyn MessageFilter>>::try_from(&rules)?;
+        let count = filters.len();
+
+        *self.filters.write().unwrap_or_else(|e| e.into_inner()) = filters;
+        Ok(count)
+    }
+
+    /// Apply method runs the filters on a message, stopping at the first rejection
+    ///
+    /// Args:
+    ///     - message: message to filter
+    pub fn apply(&self, message: &mut Message) -> Result<(), anyhow::Error> {
+        self.filters
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .try_for_each(|filter| filter.apply(message))
+    }
+}
+
+// MessageHook implementation for FilterPipeline struct: the filters are applied to the received messages
+impl MessageHook for FilterPipeline {
+    fn name(&self) -> &str {
+        "filters"
+    }
+
+    fn on_receive(&self, message: &mut Message) -> Result<(), anyhow::Error> {
+        self.apply(message)
+    }
+}
+
+/// Reload task function reloads the filters every time the server receives SIGHUP
+///
+/// Args:
+///     - ctx: context of the server with the filters
+#[cfg(unix)]
+pub async fn reload_task(
+    ctx: std::sync::Arc<crate::context::Context>,
+) -> Result<(), anyhow::Error> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = signal(SignalKind::hangup())?;
+    while hangup.recv().await.is_some() {
+        match ctx.filters.reload() {
+            Ok(count) => info!("Reloaded the filters: {count} active"),
+            Err(e) => warn!(
+                ?e,
+                "failed to reload the filters, the previous ones are kept"
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Apply function runs a filter on a text and returns the filtered text
+    fn apply(filter: &dyn MessageFilter, text: &str) -> Result<String, anyhow::Error> {
+        let mut message = Message::new("alice", text, "general");
+        filter.apply(&mut message)?;
+        Ok(message.message)
+    }
+
+    #[test]
+    fn blocklist_masks_whole_words_ignoring_the_case() {
+        let blocklist = Blocklist::new(&["darn".to_string()], '*').unwrap();
+
+        assert_eq!(
+            apply(&blocklist, "Darn it, darn!").unwrap(),
+            "**** it, ****!"
+        );
+        assert_eq!(apply(&blocklist, "darnit").unwrap(), "darnit");
+    }
+
+    #[test]
+    fn blocklist_masks_words_starting_or_ending_with_symbols() {
+        let words = ["@ss".to_string(), "c++".to_string()];
+        let blocklist = Blocklist::new(&words, '#').unwrap();
+
+        assert_eq!(apply(&blocklist, "you @ss").unwrap(), "you ###");
+        assert_eq!(apply(&blocklist, "I like c++.").unwrap(), "I like ###.");
+        assert_eq!(apply(&blocklist, "@ssets").unwrap(), "@ssets");
+    }
+
+    #[test]
+    fn regex_reject_rejects_the_matching_messages() {
+        let filter = RegexReject {
+            patterns: vec![Regex::new(r"(?i)buy\s+now").unwrap()],
+        };
+
+        assert!(apply(&filter, "BUY  now!").is_err());
+        assert_eq!(apply(&filter, "buying").unwrap(), "buying");
+    }
+
+    #[test]
+    fn link_blocker_allows_only_the_allowed_domains() {
+        let filter = LinkBlocker::new(&["Example.com".to_string()]).unwrap();
+
+        assert!(apply(&filter, "see https://docs.example.com/page").is_ok());
+        assert!(apply(&filter, "see www.example.com").is_ok());
+        assert!(apply(&filter, "see http://example.com.evil.org").is_err());
+        assert!(apply(&filter, "see www.other.org").is_err());
+        assert!(apply(&filter, "no links here.").is_ok());
+    }
+
+    #[test]
+    fn pipeline_reload_keeps_the_filters_if_the_file_is_invalid() {
+        let path = std::env::temp_dir().join(format!("chat-filters-{}.toml", std::process::id()));
+        fs::write(&path, "blocklist = [\"darn\"]\nblock_links = true\n").unwrap();
+
+        let pipeline = FilterPipeline::load(Some(&path)).unwrap();
+        let mut message = Message::new("alice", "darn", "general");
+        pipeline.apply(&mut message).unwrap();
+        assert_eq!(message.message, "****");
+
+        // An invalid file is an error and the previous filters stay active
+        fs::write(&path, "blocklist = 1\n").unwrap();
+        assert!(pipeline.reload().is_err());
+        let mut message = Message::new("alice", "www.example.com", "general");
+        assert!(pipeline.apply(&mut message).is_err());
+
+        fs::write(&path, "reject_patterns = [\"spam\"]\n").unwrap();
+        assert_eq!(pipeline.reload().unwrap(), 1);
+        let mut message = Message::new("alice", "darn www.example.com", "general");
+        pipeline.apply(&mut message).unwrap();
+        assert_eq!(message.message, "darn www.example.com");
+
+        fs::remove_file(&path).unwrap();
+    }
+}
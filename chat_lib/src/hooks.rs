@@ -0,0 +1,111 @@
+/// Hooks module: extension points of the server called while the messages are processed
+// Necessary imports
+use crate::messages::Message;
+use std::{borrow::Cow, fmt::Debug, sync::Arc};
+
+/// MessageHook trait implemented by the extensions of the server (bots, filters, integrations...),
+/// every method has a default implementation so that a hook only implements the stages it needs
+pub trait MessageHook: Debug + Send + Sync {
+    /// Name method returns the name of the hook, used in the logs
+    fn name(&self) -> &str;
+
+    /// On receive method is called when a valid message is received, before it is stored:
+    /// the hook can transform or annotate the message, an error rejects it and is sent back to the client
+    ///
+    /// Args:
+    ///     - message: received message (without id and timestamp yet)
+    fn on_receive(&self, message: &mut Message) -> Result<(), anyhow::Error> {
+        let _ = message;
+        Ok(())
+    }
+
+    /// On store method is called after the message has been stored, it must not block (slow work should be spawned)
+    ///
+    /// Args:
+    ///     - message: stored message, with its id and timestamp
+    fn on_store(&self, message: &Message) {
+        let _ = message;
+    }
+
+    /// On deliver method is called for every message sent to a client: the hook can transform or annotate
+    /// the copy sent to the client (the stored message is not changed), returning false withholds the message
+    ///
+    /// Args:
+    ///     - message: copy of the message sent to the client
+    ///     - reader: user reading the message, if known
+    fn on_deliver(&self, message: &mut Message, reader: Option<&str>) -> bool {
+        let _ = (message, reader);
+        true
+    }
+}
+
+/// HookRegistry struct containing the hooks of the server, called in the order they were registered
+#[derive(Debug, Default)]
+pub struct HookRegistry {
+    /// Registered hooks
+    hooks: Vec<Arc<dyn MessageHook>>,
+}
+
+// Methods for the HookRegistry struct
+impl HookRegistry {
+    /// Register method adds a hook to the registry
+    ///
+    /// Args:
+    ///     - hook: hook to add
+    pub fn register(&mut self, hook: Arc<dyn MessageHook>) -> &mut Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// Is empty method checks if no hook is registered
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// On receive method runs the hooks on a received message, stopping at the first rejection
+    ///
+    /// Args:
+    ///     - message: received message
+    pub fn on_receive(&self, message: &mut Message) -> Result<(), anyhow::Error> {
+        self.hooks
+            .iter()
+            .try_for_each(|hook| hook.on_receive(message))
+    }
+
+    /// On store method notifies the hooks of a stored message
+    ///
+    /// Args:
+    ///     - message: stored message
+    pub fn on_store(&self, message: &Message) {
+        self.hooks.iter().for_each(|hook| hook.on_store(message));
+    }
+
+    /// Deliver method runs the hooks on a message sent to a client, returns None if a hook withholds it.
+    /// The message is only copied when there are hooks
+    ///
+    /// Args:
+    ///     - message: message sent to the client
+    ///     - reader: user reading the message, if known
+    pub fn deliver<'a>(
+        &self,
+        message: &'a Message,
+        reader: Option<&str>,
+    ) -> Option<Cow<'a, Message>> {
+        if self.hooks.is_empty() {
+            return Some(Cow::Borrowed(message));
+        }
+
+        let mut message = message.clone();
+        for hook in &self.hooks {
+            if !hook.on_deliver(&mut message, reader) {
+                return None;
+            }
+        }
+        Some(Cow::Owned(message))
+    }
+
+    /// Names method returns the names of the registered hooks
+    pub fn names(&self) -> Vec<&str> {
+        self.hooks.iter().map(|hook| hook.name()).collect()
+    }
+}
@@ -0,0 +1,274 @@
+/// Import module: loading of JSON Lines transcripts (as written by the export and the store) into the history
+// Necessary imports
+use crate::{
+    config::ServerConfig, context::Context, messages::Message, storage::Store,
+    validation::ValidationPolicy,
+};
+use anyhow::{anyhow, Context as _};
+use serde::Deserialize;
+use std::{collections::HashSet, path::Path, sync::atomic::Ordering};
+
+/// ImportReport struct representing the outcome of an import
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// Number of messages added to the history
+    pub imported: usize,
+
+    /// Ids of the messages rejected because the history (or the archive) already has a message with the same id
+    pub duplicates: Vec<u64>,
+}
+
+// ArchivedId struct representing the id of an archived message, the rest of the message is not needed
+#[derive(Deserialize)]
+struct ArchivedId {
+    id: u64,
+}
+
+/// Parse function reads the messages of a JSON Lines transcript, a message per line (blank lines are skipped),
+/// the messages must follow the validation policy like the sent ones
+///
+/// Args:
+///     - content: content of the transcript
+///     - policy: rules the messages must follow
+pub fn parse(content: &str, policy: &ValidationPolicy) -> Result<Vec<Message>, anyhow::Error> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let mut message = serde_json::from_str::<Message>(line)
+                .with_context(|| format!("Invalid message at line {}", i + 1))?;
+            // The ids are preserved, so every message must come with its own
+            if message.id == 0 {
+                return Err(anyhow!("Missing message id at line {}", i + 1));
+            }
+            policy
+                .validate(&mut message)
+                .with_context(|| format!("Invalid message at line {}", i + 1))?;
+            Ok(message)
+        })
+        .collect()
+}
+
+/// Merge function adds the imported messages to the history, keeping it ordered by id,
+/// the messages whose id is already taken (also by a previous line of the import or by an archived message) are rejected
+///
+/// Args:
+///     - messages: history
+///     - imported: messages to add, with their authors, timestamps and ids
+///     - archived: ids of the messages moved to the archive by the retention policy
+pub fn merge(
+    messages: &mut Vec<Message>,
+    imported: Vec<Message>,
+    archived: HashSet<u64>,
+) -> ImportReport {
+    let mut ids = archived;
+    ids.extend(messages.iter().map(|msg| msg.id));
+    let mut report = ImportReport::default();
+
+    for message in imported {
+        if ids.insert(message.id) {
+            messages.push(message);
+            report.imported += 1;
+        } else {
+            report.duplicates.push(message.id);
+        }
+    }
+    messages.sort_by_key(|msg| msg.id);
+
+    report
+}
+
+/// Import function adds the imported messages to the state of a running server and saves them,
+/// the new messages then get ids after the imported ones. The hooks are not notified: the messages are not new
+///
+/// Args:
+///     - ctx: context of the server
+///     - imported: messages to add
+pub async fn import(ctx: &Context, imported: Vec<Message>) -> Result<ImportReport, anyhow::Error> {
+    let archived = archived_ids(ctx.config.retention.archive.as_deref()).await?;
+    let mut msgs = ctx.state.lock().await;
+    let (len, newest) = (msgs.len(), msgs.last().map_or(0, |last| last.id));
+
+    let report = merge(&mut msgs, imported, archived);
+    if report.imported == 0 {
+        return Ok(report);
+    }
+
+    // The store only needs the new lines if all the imported messages come after the history (it is ordered by id)
+    if let Some(store) = &ctx.store {
+        if msgs[len].id > newest {
+            for message in &msgs[len..] {
+                store.append(message).await?;
+            }
+        } else {
+            store.rewrite(&msgs).await?;
+        }
+    }
+    if let Some(last) = msgs.last() {
+        ctx.last_id.fetch_max(last.id, Ordering::SeqCst);
+    }
+
+    Ok(report)
+}
+
+/// Import file function adds the messages of a transcript to the store of the configuration while the server is not running
+///
+/// Args:
+///     - config: configuration of the server (the store, the archive and the validation policy)
+///     - file: JSON Lines transcript
+pub async fn import_file(
+    config: &ServerConfig,
+    file: &Path,
+) -> Result<ImportReport, anyhow::Error> {
+    let store = config.storage.path.as_deref().ok_or(anyhow!(
+        "The import needs a persistent storage, set storage.path"
+    ))?;
+    let content = tokio::fs::read_to_string(file)
+        .await
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let imported = parse(&content, &config.validation)
+        .with_context(|| format!("Invalid transcript {}", file.display()))?;
+
+    let (store, mut messages) = Store::open(store).await?;
+    let archived = archived_ids(config.retention.archive.as_deref()).await?;
+    let report = merge(&mut messages, imported, archived);
+    if report.imported > 0 {
+        store.rewrite(&messages).await?;
+    }
+
+    Ok(report)
+}
+
+// Archived ids function reads the ids of the messages in the archive, so that they are not given to imported messages
+async fn archived_ids(archive: Option<&Path>) -> Result<HashSet<u64>, anyhow::Error> {
+    let content = match archive {
+        Some(path) => match tokio::fs::read_to_string(path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashSet::new()),
+            Err(e) => {
+                return Err(anyhow::Error::new(e)
+                    .context(format!("Failed to read archive {}", path.display())));
+            }
+        },
+        None => return Ok(HashSet::new()),
+    };
+
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str::<ArchivedId>(line).map(|archived| archived.id))
+        .collect::<Result<_, _>>()
+        .context("Corrupted archive")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Message function builds a message with an id
+    fn message(id: u64, text: &str) -> Message {
+        let mut message = Message::new("alice", text, "general");
+        message.id = id;
+        message.timestamp = 1_600_000_000 + id;
+        message
+    }
+
+    // Summary function reduces the messages to their ids and texts to compare them
+    fn summary(messages: &[Message]) -> Vec<(u64, &str)> {
+        messages
+            .iter()
+            .map(|msg| (msg.id, msg.message.as_str()))
+            .collect()
+    }
+
+    // Jsonl function writes the messages as a transcript
+    fn jsonl(messages: &[Message]) -> String {
+        messages
+            .iter()
+            .map(|msg| serde_json::to_string(msg).unwrap() + "\n")
+            .collect()
+    }
+
+    #[test]
+    fn parse_reads_messages_and_skips_blank_lines() {
+        let content = jsonl(&[message(3, "a"), message(1, "b")]).replace('\n', "\n\n");
+        let messages = parse(&content, &ValidationPolicy::default()).unwrap();
+
+        assert_eq!(summary(&messages), vec![(3, "a"), (1, "b")]);
+        assert_eq!(messages[0].timestamp, 1_600_000_003);
+    }
+
+    #[test]
+    fn parse_rejects_id_zero_and_invalid_lines() {
+        let policy = ValidationPolicy::default();
+        let error = parse(&jsonl(&[message(1, "a"), message(0, "b")]), &policy).unwrap_err();
+        assert_eq!(error.to_string(), "Missing message id at line 2");
+
+        let error = parse("{\"id\":1}\n", &policy).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid message at line 1");
+    }
+
+    #[test]
+    fn parse_validates_and_sanitizes_the_messages() {
+        let policy = ValidationPolicy::default();
+        let messages = parse(&jsonl(&[message(1, "a\u{7}b")]), &policy).unwrap();
+        assert_eq!(summary(&messages), vec![(1, "ab")]);
+
+        let mut invalid = message(2, "c");
+        invalid.user = "x".repeat(policy.max_name_len + 1);
+        let error = parse(&jsonl(&[message(1, "a"), invalid]), &policy).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid message at line 2");
+        assert!(format!("{error:#}").contains("Username is too long"));
+    }
+
+    #[test]
+    fn merge_rejects_duplicates_in_the_import() {
+        let mut history = Vec::new();
+        let report = merge(
+            &mut history,
+            vec![message(1, "a"), message(2, "b"), message(1, "again")],
+            HashSet::new(),
+        );
+
+        assert_eq!(report.imported, 2);
+        assert_eq!(report.duplicates, vec![1]);
+        assert_eq!(summary(&history), vec![(1, "a"), (2, "b")]);
+    }
+
+    #[test]
+    fn merge_rejects_duplicates_of_the_history_and_keeps_the_order() {
+        let mut history = vec![message(2, "b"), message(5, "e")];
+        let report = merge(
+            &mut history,
+            vec![
+                message(6, "f"),
+                message(5, "copy"),
+                message(1, "a"),
+                message(3, "c"),
+            ],
+            HashSet::new(),
+        );
+
+        assert_eq!(report.imported, 3);
+        assert_eq!(report.duplicates, vec![5]);
+        assert_eq!(
+            summary(&history),
+            vec![(1, "a"), (2, "b"), (3, "c"), (5, "e"), (6, "f")]
+        );
+    }
+
+    #[test]
+    fn merge_rejects_the_ids_of_the_archived_messages() {
+        let mut history = vec![message(5, "e")];
+        let report = merge(
+            &mut history,
+            vec![message(1, "a"), message(2, "b")],
+            HashSet::from([1]),
+        );
+
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.duplicates, vec![1]);
+        assert_eq!(summary(&history), vec![(2, "b"), (5, "e")]);
+    }
+}
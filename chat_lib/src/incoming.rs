@@ -0,0 +1,193 @@
+/// Incoming module: incoming webhooks posting the messages of external systems into the chat
+// Necessary imports
+use crate::{
+    admin::token_eq,
+    context::Context,
+    messages::{Message, DEFAULT_ROOM},
+    request::{Method, Request},
+    response::{post_message, Response, Status},
+};
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// IncomingWebhook struct representing an incoming webhook: the messages posted with its token are sent by its user into its room
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IncomingWebhook {
+    /// Secret token of the webhook, part of its url (generated by the server)
+    #[serde(default)]
+    pub token: String,
+
+    /// Username the messages are sent as
+    pub user: String,
+
+    /// Room the messages are sent to
+    #[serde(default = "default_room")]
+    pub room: String,
+}
+
+/// Payload struct representing the body of a request to an incoming webhook
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Payload {
+    /// Text of the message
+    pub text: String,
+}
+
+/// IncomingWebhooks struct containing the incoming webhooks created by the admins
+#[derive(Debug, Default)]
+pub struct IncomingWebhooks {
+    /// File the webhooks are saved to (JSON), they are only kept in memory if None
+    path: Option<PathBuf>,
+
+    /// Active webhooks
+    webhooks: Mutex<Vec<IncomingWebhook>>,
+}
+
+// Methods for the IncomingWebhooks struct
+impl IncomingWebhooks {
+    /// Load method reads the saved webhooks, there are none if the file doesn't exist yet
+    ///
+    /// Args:
+    ///     - path: file of the webhooks
+    pub fn load(path: Option<&Path>) -> Result<Self, anyhow::Error> {
+        let webhooks = match path {
+            Some(path) if path.exists() => {
+                let content = fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                serde_json::from_str(&content)
+                    .with_context(|| format!("Invalid incoming webhooks file {}", path.display()))?
+            }
+            _ => Vec::new(),
+        };
+
+        Ok(Self {
+            path: path.map(Path::to_path_buf),
+            webhooks: Mutex::new(webhooks),
+        })
+    }
+
+    /// Create method adds a webhook with a new token and returns it
+    ///
+    /// Args:
+    ///     - webhook: user and room of the webhook, the token is replaced
+    pub fn create(&self, mut webhook: IncomingWebhook) -> Result<IncomingWebhook, anyhow::Error> {
+        webhook.token = new_token()?;
+
+        let mut webhooks = self.lock();
+        webhooks.push(webhook.clone());
+        self.save(&webhooks)?;
+
+        Ok(webhook)
+    }
+
+    /// Revoke method deletes the webhook of a token, returns false if there was none
+    ///
+    /// Args:
+    ///     - token: token of the webhook
+    pub fn revoke(&self, token: &str) -> Result<bool, anyhow::Error> {
+        let mut webhooks = self.lock();
+        let len = webhooks.len();
+        webhooks.retain(|webhook| webhook.token != token);
+
+        if webhooks.len() == len {
+            return Ok(false);
+        }
+        self.save(&webhooks)?;
+        Ok(true)
+    }
+
+    /// List method returns the active webhooks
+    pub fn list(&self) -> Vec<IncomingWebhook> {
+        self.lock().clone()
+    }
+
+    /// Find method returns the webhook of a token, if exists
+    ///
+    /// Args:
+    ///     - token: token of the webhook
+    pub fn find(&self, token: &str) -> Option<IncomingWebhook> {
+        self.lock()
+            .iter()
+            .find(|webhook| token_eq(&webhook.token, token))
+            .cloned()
+    }
+
+    // Lock method returns the webhooks, a poisoned lock is recovered because the list is always consistent
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<IncomingWebhook>> {
+        self.webhooks.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Save method writes the webhooks to their file, replacing it at once so that a crash can't truncate it
+    fn save(&self, webhooks: &[IncomingWebhook]) -> Result<(), anyhow::Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let tmp = PathBuf::from(format!("{}.tmp", path.display()));
+        fs::write(&tmp, serde_json::to_string_pretty(webhooks)?)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+
+        Ok(())
+    }
+}
+
+/// Get response function generates the response of a request to an incoming webhook (POST /hooks/<token>)
+///
+/// Args:
+///     - req: request from the external system
+///     - ctx: context of the server
+pub async fn get_response(req: &Request, ctx: &Context) -> Result<Response, anyhow::Error> {
+    let token = req.path().trim_start_matches("/hooks/");
+    let webhook = match (req.method, ctx.incoming.find(token)) {
+        (Method::Post, Some(webhook)) => webhook,
+        _ => {
+            return Ok(Response::new(
+                Status::NotFound,
+                "text/plain",
+                "Not Found".to_string(),
+            ));
+        }
+    };
+
+    let payload = req
+        .body
+        .as_deref()
+        .map(serde_json::from_str::<Payload>)
+        .transpose();
+    match payload {
+        Ok(Some(payload)) => {
+            let msg = Message::new(&webhook.user, &payload.text, &webhook.room);
+            post_message(ctx, msg, req.peer.map(|peer| peer.ip())).await
+        }
+        Ok(None) => Ok(Response::new(
+            Status::BadRequest,
+            "text/plain",
+            "Missing payload".to_string(),
+        )),
+        Err(e) => Ok(Response::new(
+            Status::BadRequest,
+            "text/plain",
+            format!("Invalid payload: {e}"),
+        )),
+    }
+}
+
+// New token function generates a random token of 32 hex characters from the random generator of the system
+fn new_token() -> Result<String, anyhow::Error> {
+    let mut bytes = [0; 16];
+    getrandom::fill(&mut bytes).map_err(|e| anyhow::anyhow!("Failed to generate a token: {e}"))?;
+
+    Ok(hex::encode(bytes))
+}
+
+// Default value of the room field for webhooks created without one
+fn default_room() -> String {
+    DEFAULT_ROOM.to_string()
+}
@@ -1,35 +1,213 @@
 /// Library for the chat app client and server
 // Necessary imports
-use messages::State;
-use request::{parse_request, Request};
-use response::{get_response, Response};
-use tokio::{io::AsyncWriteExt, net::TcpStream};
+use anyhow::Context as _;
+use config::LimitsConfig;
+use context::Context;
+use request::{parse_request, Request, RequestError};
+use response::{get_response, Response, Status};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    time::timeout,
+};
+use tracing::{error, info, info_span, Instrument};
 
 // Modules of the library
+pub mod admin;
 pub mod args;
+pub mod bot;
+pub mod client;
+pub mod config;
+pub mod context;
+pub mod export;
+pub mod filter;
+pub mod hooks;
+pub mod import;
+pub mod incoming;
+pub mod logging;
 pub mod messages;
+pub mod metrics;
+pub mod moderation;
+pub mod rate_limit;
 pub mod request;
 pub mod response;
+pub mod retention;
+pub mod search;
+pub mod shutdown;
+pub mod storage;
+pub mod validation;
+pub mod webhook;
 
 /// Default IP_ADDR of the server: 127.0.0.1 (localhost)
-pub static IP_ADDR: &str = "127.0.0.1";
+pub static IP_ADDR: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
 
 /// Default PORT of the server: 8080
-pub static PORT: &str = "8080";
+pub static PORT: u16 = 8080;
+
+/// Connections refused at the same time when the server is saturated, the ones beyond are closed without a response
+pub static MAX_REJECTIONS: usize = 64;
+
+/// Time given to the refusal of a connection
+pub static REJECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Pause after a failed accept (e.g. too many open files), before accepting again
+pub static ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Bind listener function creates the tcp listener of the server
+///
+/// Args:
+///     - socket_addr: socket address to listen on
+///     - dual_stack: whether an IPv6 listener also accepts IPv4 connections (e.g. on [::])
+pub fn bind_listener(
+    socket_addr: SocketAddr,
+    dual_stack: bool,
+) -> Result<TcpListener, anyhow::Error> {
+    let socket = Socket::new(
+        Domain::for_address(socket_addr),
+        Type::STREAM,
+        Some(Protocol::TCP),
+    )?;
+
+    if socket_addr.is_ipv6() {
+        socket.set_only_v6(!dual_stack)?;
+    }
+    #[cfg(not(windows))]
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+
+    socket.bind(&socket_addr.into())?;
+    socket.listen(1024)?;
+
+    Ok(TcpListener::from_std(socket.into())?)
+}
 
 /// Handle connection function handles the connection for the server
 ///
 /// Args:
 ///     - stream: tcp stream of the connection
-///     - state: state of the server with all the messages
-pub async fn handle_connection(mut stream: TcpStream, state: State) -> Result<(), anyhow::Error> {
-    // Parse the request from the client
-    let request: Request = parse_request(&mut stream).await?;
-    // Generate a response
-    let response: Response = get_response(request, state).await?;
+///     - ctx: context of the server with the messages and the configuration
+pub async fn handle_connection(
+    mut stream: TcpStream,
+    ctx: Arc<Context>,
+) -> Result<(), anyhow::Error> {
+    let peer = stream
+        .peer_addr()
+        .map_or_else(|_| "unknown".to_string(), |peer| peer.to_string());
+    let span = info_span!("connection", %peer);
+
+    ctx.metrics.connection_opened();
+    let result = async {
+        // Parse the request from the client, the requests refused while they are read are answered before closing
+        let request: Request = match parse_request(&mut stream, &ctx.config.limits).await {
+            Ok(request) => request,
+            Err(e) => {
+                if let Some(error) = e.downcast_ref::<RequestError>() {
+                    let response = Response::new(error.status, "text/plain", error.to_string());
+                    let _ = refuse(&mut stream, &response).await;
+                }
+                return Err(e);
+            }
+        };
+        let (method, path) = (request.method, request.path().to_string());
+
+        // Every request has its own span with the route of the path: the query string can contain usernames
+        // and the paths of the webhooks contain their secret tokens
+        let span = info_span!("request", %method, route = %metrics::route(&path));
+        async {
+            // Generate a response, the time it takes is recorded with its status. A failure of the server
+            // (e.g. the store can't be written) is answered with 500, so that the client knows the request was lost
+            let start = Instant::now();
+            let response: Response = match get_response(request, &ctx).await {
+                Ok(response) => response,
+                Err(e) => {
+                    error!(error = %format!("{e:#}"), "failed to handle the request");
+                    Response::new(
+                        Status::InternalServerError,
+                        "text/plain",
+                        "Internal server error".to_string(),
+                    )
+                }
+            };
+            ctx.metrics
+                .request(method, &path, response.status(), start.elapsed());
+
+            // Send the response
+            let bytes = write_response(&mut stream, &response, &ctx.config.limits).await?;
+            info!(
+                status = response.status().code(),
+                duration_ms = start.elapsed().as_secs_f64() * 1000.0,
+                bytes,
+                "request served"
+            );
+            Ok(())
+        }
+        .instrument(span)
+        .await
+    }
+    .instrument(span.clone())
+    .await;
+    ctx.metrics.connection_closed(result.is_err());
+
+    if let Err(e) = &result {
+        span.in_scope(|| info!(error = %format!("{e:#}"), "failed to handle the connection"));
+    }
+    result
+}
+
+/// Reject connection function answers a connection the server has no room for with 503,
+/// without reading the request and within REJECT_TIMEOUT
+///
+/// Args:
+///     - stream: tcp stream of the connection
+pub async fn reject_connection(mut stream: TcpStream) -> Result<(), anyhow::Error> {
+    let response = Response::new(
+        Status::ServiceUnavailable,
+        "text/plain",
+        "Server is busy".to_string(),
+    )
+    .header("Retry-After", "1");
 
-    // Send the response
-    stream.write_all(response.to_string().as_bytes()).await?;
+    refuse(&mut stream, &response).await
+}
+
+// Refuse function sends a response without reading the rest of the request, within REJECT_TIMEOUT
+async fn refuse(stream: &mut TcpStream, response: &Response) -> Result<(), anyhow::Error> {
+    timeout(REJECT_TIMEOUT, async {
+        stream.write_all(response.to_string().as_bytes()).await?;
+
+        // Closing with unread data would reset the connection before the client reads the response,
+        // so the request is discarded until the client closes its side
+        stream.shutdown().await?;
+        let mut buf = [0; 1024];
+        while stream.read(&mut buf).await? > 0 {}
+
+        Ok::<_, std::io::Error>(())
+    })
+    .await
+    .context("Timed out refusing the connection")??;
 
     Ok(())
 }
+
+// Write response function sends the response to the client before the write timeout, returns the bytes sent
+async fn write_response(
+    stream: &mut TcpStream,
+    response: &Response,
+    limits: &LimitsConfig,
+) -> Result<usize, anyhow::Error> {
+    let response = response.to_string();
+    timeout(
+        Duration::from_secs(limits.write_timeout_secs),
+        stream.write_all(response.as_bytes()),
+    )
+    .await
+    .context("Timed out writing the response")??;
+
+    Ok(response.len())
+}
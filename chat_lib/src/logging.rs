@@ -0,0 +1,44 @@
+/// Logging module: setup of the tracing subscriber of the binaries
+// Necessary imports
+use crate::config::{LogConfig, LogFormat};
+use anyhow::{anyhow, Context as _};
+use std::{env, fs::OpenOptions, sync::Mutex};
+use tracing_subscriber::{fmt, EnvFilter};
+
+/// Init function installs the global subscriber of the logs: RUST_LOG overrides the configured level,
+/// the logs go to the configured file (without colors) or to stdout
+///
+/// Args:
+///     - config: settings of the logs
+pub fn init(config: &LogConfig) -> Result<(), anyhow::Error> {
+    let filter = match env::var("RUST_LOG") {
+        Ok(directives) if !directives.is_empty() => EnvFilter::try_new(&directives)
+            .with_context(|| format!("Invalid RUST_LOG {directives:?}"))?,
+        _ => EnvFilter::try_new(&config.level)
+            .with_context(|| format!("Invalid log level {:?}", config.level))?,
+    };
+    let builder = fmt().with_env_filter(filter);
+
+    let file = match &config.file {
+        Some(path) => Some(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open the log file {}", path.display()))?,
+        ),
+        None => None,
+    };
+
+    let result = match (config.format, file) {
+        (LogFormat::Pretty, None) => builder.try_init(),
+        (LogFormat::Json, None) => builder.json().try_init(),
+        (LogFormat::Pretty, Some(file)) => builder
+            .with_ansi(false)
+            .with_writer(Mutex::new(file))
+            .try_init(),
+        (LogFormat::Json, Some(file)) => builder.json().with_writer(Mutex::new(file)).try_init(),
+    };
+
+    result.map_err(|e| anyhow!("Failed to initialize the logs: {e}"))
+}
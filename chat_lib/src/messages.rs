@@ -1,17 +1,123 @@
 /// Messages module
 // Necessary imports
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
 use tokio::sync::Mutex;
 
+/// Default room joined by every client: general
+pub static DEFAULT_ROOM: &str = "general";
+
 /// Type State is the alias of Arc<Mutex<Vec<Messages>>> and holds the state of the server
 pub type State = Arc<Mutex<Vec<Message>>>;
 
+/// Message struct representing a chat message
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
+    /// Identifier of the message assigned by the server
+    #[serde(default)]
+    pub id: u64,
+
     /// Sender's username
     pub user: String,
 
     /// Content of the message
     pub message: String,
+
+    /// Unix timestamp (in seconds) assigned by the server when the message is received
+    #[serde(default)]
+    pub timestamp: u64,
+
+    /// Room the message was sent to
+    #[serde(default = "default_room")]
+    pub room: String,
+
+    /// Recipient of a direct message, None if the message is visible to the whole room
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+
+    /// Kind of the message
+    #[serde(default)]
+    pub kind: MessageKind,
+
+    /// Annotations added by the hooks of the server (key and value)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<String, String>,
+}
+
+/// MessageKind enum enumerates the kinds of message a user can send
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageKind {
+    /// Plain text message
+    #[default]
+    Text,
+    /// Action performed by the user (/me)
+    Action,
+}
+
+// Methods for the Message struct
+impl Message {
+    /// Constructor method, the timestamp is left to the server
+    ///
+    /// Args:
+    ///     - user: sender's username
+    ///     - message: content of the message
+    ///     - room: room the message is sent to
+    pub fn new(user: &str, message: &str, room: &str) -> Self {
+        Self {
+            id: 0,
+            user: user.to_string(),
+            message: message.to_string(),
+            timestamp: 0,
+            room: room.to_string(),
+            to: None,
+            kind: MessageKind::Text,
+            annotations: BTreeMap::new(),
+        }
+    }
+
+    /// Is visible to method checks if a user is allowed to read the message (direct messages are only shown to sender and recipient).
+    /// There are no accounts, so this is not access control: anyone can read as any user
+    ///
+    /// Args:
+    ///     - user: username of the reader
+    pub fn is_visible_to(&self, user: &str) -> bool {
+        match &self.to {
+            Some(to) => to == user || self.user == user,
+            None => true,
+        }
+    }
+
+    /// Mentions method checks if the message mentions a user with @username
+    ///
+    /// Args:
+    ///     - user: username to look for
+    pub fn mentions(&self, user: &str) -> bool {
+        let mention = format!("@{user}");
+
+        // The mention must not be followed by other characters of a longer username
+        self.message.match_indices(&mention).any(|(i, _)| {
+            self.message[i + mention.len()..]
+                .chars()
+                .next()
+                .is_none_or(|c| !c.is_alphanumeric() && c != '_' && c != '-')
+        })
+    }
+}
+
+// Default value of the room field for messages sent without one
+fn default_room() -> String {
+    DEFAULT_ROOM.to_string()
+}
+
+/// Unix now function returns the current unix timestamp in seconds
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
 }
@@ -0,0 +1,245 @@
+/// Metrics module: counters of the server exposed in the Prometheus text format
+// Necessary imports
+use crate::{messages::Message, request::Method, response::Status, retention};
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+/// Upper bounds in seconds of the buckets of the latency histograms
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Histogram struct counting observations in cumulative buckets
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    /// Observations lower or equal to every bound of LATENCY_BUCKETS
+    buckets: [u64; LATENCY_BUCKETS.len()],
+
+    /// Sum of the observations
+    sum: f64,
+
+    /// Number of observations
+    count: u64,
+}
+
+/// Metrics struct containing the counters of the server
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Accepted connections
+    connections_total: AtomicU64,
+
+    /// Connections being handled
+    connections_active: AtomicU64,
+
+    /// Connections refused because the server was saturated
+    connections_rejected: AtomicU64,
+
+    /// Connections closed without a response (invalid requests, timeouts...)
+    connection_errors: AtomicU64,
+
+    /// Messages published
+    messages_total: AtomicU64,
+
+    /// Requests by method, route and status code
+    requests: Mutex<BTreeMap<(&'static str, &'static str, u16), u64>>,
+
+    /// Latency of the requests by route
+    latency: Mutex<BTreeMap<&'static str, Histogram>>,
+}
+
+// Methods for the Histogram struct
+impl Histogram {
+    // Observe method records a value
+    fn observe(&mut self, value: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if value <= bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+// Methods for the Metrics struct
+impl Metrics {
+    /// Connection opened method records an accepted connection
+    pub fn connection_opened(&self) {
+        self.connections_total.fetch_add(1, Ordering::Relaxed);
+        self.connections_active.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Connection closed method records the end of an accepted connection
+    ///
+    /// Args:
+    ///     - failed: whether the connection ended without a response
+    pub fn connection_closed(&self, failed: bool) {
+        self.connections_active.fetch_sub(1, Ordering::Relaxed);
+        if failed {
+            self.connection_errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Connection rejected method records a connection refused because the server was saturated
+    pub fn connection_rejected(&self) {
+        self.connections_rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Message published method records a new message
+    pub fn message_published(&self) {
+        self.messages_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Request method records a request with its response status and how long it took
+    ///
+    /// Args:
+    ///     - method: method of the request
+    ///     - path: path of the request, reduced to its route
+    ///     - status: status of the response
+    ///     - elapsed: time spent generating the response
+    pub fn request(&self, method: Method, path: &str, status: Status, elapsed: Duration) {
+        let route = route(path);
+        let method = method.as_str();
+
+        *self
+            .requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry((method, route, status.code()))
+            .or_default() += 1;
+        self.latency
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(route)
+            .or_default()
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Render method writes the metrics in the Prometheus text format
+    ///
+    /// Args:
+    ///     - msgs: messages of the state, measured at every scrape
+    pub fn render(&self, msgs: &[Message]) -> String {
+        let mut out = String::new();
+
+        let counters = [
+            (
+                "chat_connections_total",
+                "counter",
+                "Accepted connections",
+                self.connections_total.load(Ordering::Relaxed),
+            ),
+            (
+                "chat_connections_active",
+                "gauge",
+                "Connections being handled",
+                self.connections_active.load(Ordering::Relaxed),
+            ),
+            (
+                "chat_connections_rejected_total",
+                "counter",
+                "Connections refused because the server was saturated",
+                self.connections_rejected.load(Ordering::Relaxed),
+            ),
+            (
+                "chat_connection_errors_total",
+                "counter",
+                "Connections closed without a response",
+                self.connection_errors.load(Ordering::Relaxed),
+            ),
+            (
+                "chat_messages_total",
+                "counter",
+                "Messages published since the start",
+                self.messages_total.load(Ordering::Relaxed),
+            ),
+            (
+                "chat_state_messages",
+                "gauge",
+                "Messages kept in memory",
+                msgs.len() as u64,
+            ),
+            (
+                "chat_state_bytes",
+                "gauge",
+                "Estimated memory used by the messages",
+                msgs.iter().map(retention::size).sum::<usize>() as u64,
+            ),
+        ];
+        for (name, kind, help, value) in counters {
+            let _ = writeln!(
+                out,
+                "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}"
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP chat_requests_total Requests by method, route and status\n# TYPE chat_requests_total counter"
+        );
+        for ((method, route, status), count) in self
+            .requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+        {
+            let _ = writeln!(
+                out,
+                r#"chat_requests_total{{method="{method}",route="{route}",status="{status}"}} {count}"#
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP chat_request_duration_seconds Latency of the requests by route\n# TYPE chat_request_duration_seconds histogram"
+        );
+        for (route, histogram) in self
+            .latency
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+        {
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                let _ = writeln!(
+                    out,
+                    r#"chat_request_duration_seconds_bucket{{route="{route}",le="{bound}"}} {count}"#
+                );
+            }
+            let _ = writeln!(
+                out,
+                "chat_request_duration_seconds_bucket{{route=\"{route}\",le=\"+Inf\"}} {count}\nchat_request_duration_seconds_sum{{route=\"{route}\"}} {sum}\nchat_request_duration_seconds_count{{route=\"{route}\"}} {count}",
+                count = histogram.count,
+                sum = histogram.sum,
+            );
+        }
+
+        out
+    }
+}
+
+/// Route function reduces a path to the route serving it, so that ids and tokens don't create new series
+/// (nor end up in the logs)
+///
+/// Args:
+///     - path: path of the request, without the query string
+pub fn route(path: &str) -> &'static str {
+    match path {
+        "/messages" => "/messages",
+        "/messages/search" => "/messages/search",
+        "/export" => "/export",
+        "/policy" => "/policy",
+        "/metrics" => "/metrics",
+        "/healthz" => "/healthz",
+        "/readyz" => "/readyz",
+        path if path.starts_with("/admin/") => "/admin",
+        path if path.starts_with("/hooks/") => "/hooks",
+        _ => "other",
+    }
+}
@@ -0,0 +1,269 @@
+/// Moderation module: mutes and bans decided by the admins
+// Necessary imports
+use crate::messages::unix_now;
+use anyhow::{anyhow, Context as _};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Sanction struct representing a mute or a ban requested through the admin api
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Sanction {
+    /// Sanctioned user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+
+    /// Sanctioned address
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip: Option<IpAddr>,
+
+    /// Duration of the sanction in seconds, it is permanent if None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_secs: Option<u64>,
+}
+
+/// SanctionKind enum enumerates the sanctions: muted users can still read, banned ones can't do anything
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SanctionKind {
+    /// The user can't send messages
+    Mute,
+    /// The user can't send nor read messages
+    Ban,
+}
+
+/// Target enum enumerates who a sanction applies to
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Target {
+    /// A username
+    User(String),
+    /// A client address
+    Ip(IpAddr),
+}
+
+/// SavedSanction struct representing a sanction in the file of the sanctions
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SavedSanction {
+    /// Sanctioned user or address
+    target: Target,
+
+    /// Expiration of the sanction (unix time), it is permanent if None
+    expiration: Option<u64>,
+}
+
+/// Saved struct representing the content of the file of the sanctions
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Saved {
+    /// Muted users and addresses
+    mutes: Vec<SavedSanction>,
+
+    /// Banned users and addresses
+    bans: Vec<SavedSanction>,
+}
+
+/// Moderation struct containing the active sanctions with their expiration (unix time, None if permanent)
+#[derive(Debug, Default)]
+pub struct Moderation {
+    /// File the sanctions are saved to (JSON), they are only kept in memory if None
+    path: Option<PathBuf>,
+
+    /// Lock held while a sanction is changed and saved, so that the saves can't overwrite each other
+    saving: tokio::sync::Mutex<()>,
+
+    /// Muted users and addresses
+    mutes: Mutex<HashMap<Target, Option<u64>>>,
+
+    /// Banned users and addresses
+    bans: Mutex<HashMap<Target, Option<u64>>>,
+}
+
+// Methods for the Sanction struct
+impl Sanction {
+    // Targets method returns who the sanction applies to
+    fn targets(&self) -> Result<Vec<Target>, anyhow::Error> {
+        let targets: Vec<Target> = self
+            .user
+            .iter()
+            .map(|user| Target::User(user.clone()))
+            .chain(self.ip.map(Target::Ip))
+            .collect();
+
+        if targets.is_empty() {
+            return Err(anyhow!("A user or an ip address is required"));
+        }
+        Ok(targets)
+    }
+}
+
+// Methods for the Moderation struct
+impl Moderation {
+    /// Load method reads the saved sanctions, there are none if the file doesn't exist yet
+    ///
+    /// Args:
+    ///     - path: file of the sanctions
+    pub fn load(path: Option<&Path>) -> Result<Self, anyhow::Error> {
+        let saved: Saved = match path {
+            Some(path) if path.exists() => {
+                let content = fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                serde_json::from_str(&content)
+                    .with_context(|| format!("Invalid sanctions file {}", path.display()))?
+            }
+            _ => Saved::default(),
+        };
+        let collect = |sanctions: Vec<SavedSanction>| {
+            Mutex::new(
+                sanctions
+                    .into_iter()
+                    .map(|sanction| (sanction.target, sanction.expiration))
+                    .collect(),
+            )
+        };
+
+        Ok(Self {
+            path: path.map(Path::to_path_buf),
+            saving: tokio::sync::Mutex::new(()),
+            mutes: collect(saved.mutes),
+            bans: collect(saved.bans),
+        })
+    }
+
+    /// Apply method starts a sanction
+    ///
+    /// Args:
+    ///     - kind: mute or ban
+    ///     - sanction: who is sanctioned and for how long
+    pub async fn apply(
+        &self,
+        kind: SanctionKind,
+        sanction: &Sanction,
+    ) -> Result<(), anyhow::Error> {
+        let expiration = sanction
+            .duration_secs
+            .map(|secs| unix_now().saturating_add(secs));
+
+        let _saving = self.saving.lock().await;
+        let targets = sanction.targets()?;
+        self.sanctions(kind)
+            .extend(targets.into_iter().map(|target| (target, expiration)));
+
+        self.save().await
+    }
+
+    /// Lift method ends a sanction before its expiration, returns false if there was none
+    ///
+    /// Args:
+    ///     - kind: mute or ban
+    ///     - sanction: who is not sanctioned anymore (the duration is ignored)
+    pub async fn lift(
+        &self,
+        kind: SanctionKind,
+        sanction: &Sanction,
+    ) -> Result<bool, anyhow::Error> {
+        let _saving = self.saving.lock().await;
+        let targets = sanction.targets()?;
+        let mut lifted = false;
+        {
+            let mut sanctions = self.sanctions(kind);
+            for target in &targets {
+                lifted |= sanctions.remove(target).is_some();
+            }
+        }
+
+        if lifted {
+            self.save().await?;
+        }
+        Ok(lifted)
+    }
+
+    /// Check method returns an error describing the sanction of the user or of the address, if any
+    ///
+    /// Args:
+    ///     - kind: mute or ban
+    ///     - user: username, if known
+    ///     - ip: address of the client, if known
+    pub fn check(
+        &self,
+        kind: SanctionKind,
+        user: Option<&str>,
+        ip: Option<IpAddr>,
+    ) -> Result<(), anyhow::Error> {
+        let now = unix_now();
+        let mut sanctions = self.sanctions(kind);
+
+        // The expired sanctions are removed
+        sanctions.retain(|_, expiration| expiration.is_none_or(|expiration| expiration > now));
+
+        let targets = user
+            .map(|user| Target::User(user.to_string()))
+            .into_iter()
+            .chain(ip.map(Target::Ip));
+        for target in targets {
+            if let Some(expiration) = sanctions.get(&target) {
+                let what = match kind {
+                    SanctionKind::Mute => "muted",
+                    SanctionKind::Ban => "banned",
+                };
+                return Err(match expiration {
+                    Some(expiration) => anyhow!("You are {what} for {}s", expiration - now),
+                    None => anyhow!("You are {what}"),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    // Sanctions method returns the locked sanctions of a kind
+    fn sanctions(
+        &self,
+        kind: SanctionKind,
+    ) -> std::sync::MutexGuard<'_, HashMap<Target, Option<u64>>> {
+        let sanctions = match kind {
+            SanctionKind::Mute => &self.mutes,
+            SanctionKind::Ban => &self.bans,
+        };
+        sanctions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Save method writes the sanctions to their file, replacing it at once so that a crash can't truncate it
+    // (the expired sanctions are dropped when the file is loaded again)
+    async fn save(&self) -> Result<(), anyhow::Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let now = unix_now();
+        let saved_sanctions = |kind| {
+            self.sanctions(kind)
+                .iter()
+                .filter(|(_, expiration)| expiration.is_none_or(|expiration| expiration > now))
+                .map(|(target, expiration)| SavedSanction {
+                    target: target.clone(),
+                    expiration: *expiration,
+                })
+                .collect()
+        };
+        let saved = Saved {
+            mutes: saved_sanctions(SanctionKind::Mute),
+            bans: saved_sanctions(SanctionKind::Ban),
+        };
+
+        let tmp = PathBuf::from(format!("{}.tmp", path.display()));
+        tokio::fs::write(&tmp, serde_json::to_string_pretty(&saved)?)
+            .await
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        tokio::fs::rename(&tmp, path)
+            .await
+            .with_context(|| format!("Failed to replace {}", path.display()))?;
+
+        Ok(())
+    }
+}
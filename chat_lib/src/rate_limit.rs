@@ -0,0 +1,151 @@
+/// Rate limit module: token buckets limiting how fast the clients can send messages
+// Necessary imports
+use crate::config::{RateConfig, RateLimitConfig};
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Number of buckets after which the full ones are dropped, and then the least recently used ones
+const MAX_BUCKETS: usize = 10_000;
+
+/// TokenBucket struct representing the tokens left to a client: a message costs a token and the tokens refill over time
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    /// Tokens left
+    tokens: f64,
+
+    /// Instant of the last refill
+    last: Instant,
+}
+
+/// RateLimiter struct containing a token bucket for every key (e.g. user or ip address)
+#[derive(Debug)]
+pub struct RateLimiter<K> {
+    /// Maximum number of tokens of a bucket, i.e. messages that can be sent in a burst
+    capacity: f64,
+
+    /// Tokens added every second
+    refill_per_sec: f64,
+
+    /// Buckets of the keys
+    buckets: Mutex<HashMap<K, TokenBucket>>,
+}
+
+/// RateLimits struct containing the rate limiters of the server
+#[derive(Debug)]
+pub struct RateLimits {
+    /// Limiter keyed by the address of the client
+    pub ip: RateLimiter<IpAddr>,
+
+    /// Limiter keyed by the username
+    pub user: RateLimiter<String>,
+}
+
+// Methods for the RateLimiter struct
+impl<K: Hash + Eq> RateLimiter<K> {
+    /// Constructor method
+    ///
+    /// Args:
+    ///     - config: rate of the limiter, a rate of 0 messages per minute disables it
+    pub fn new(config: &RateConfig) -> Self {
+        Self {
+            capacity: f64::from(config.burst.max(1)),
+            refill_per_sec: f64::from(config.per_minute) / 60.0,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Check method takes a token from the bucket of the key, if there are none it returns how long to wait for the next one
+    ///
+    /// Args:
+    ///     - key: key of the bucket
+    pub fn check(&self, key: K) -> Result<(), Duration> {
+        if self.refill_per_sec == 0.0 {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+
+        // The buckets that refilled completely are the same as new ones, they are dropped to bound the memory
+        // when a new key would exceed the maximum (the bucket of the key being checked is never dropped)
+        let full = buckets.len() >= MAX_BUCKETS && !buckets.contains_key(&key);
+        if full {
+            buckets.retain(|_, bucket| {
+                bucket.tokens + now.duration_since(bucket.last).as_secs_f64() * self.refill_per_sec
+                    < self.capacity
+            });
+        }
+
+        // If they are still too many, a tenth of them is dropped starting from the least recently used:
+        // the clients sending right now (like the abusers being limited) keep their buckets
+        if full && buckets.len() >= MAX_BUCKETS {
+            let mut lasts: Vec<Instant> = buckets.values().map(|bucket| bucket.last).collect();
+            let (_, cutoff, _) =
+                lasts.select_nth_unstable(buckets.len() - MAX_BUCKETS * 9 / 10 - 1);
+            let cutoff = *cutoff;
+            buckets.retain(|_, bucket| bucket.last > cutoff);
+        }
+
+        let bucket = buckets.entry(key).or_insert(TokenBucket {
+            tokens: self.capacity,
+            last: now,
+        });
+
+        // Refill the bucket with the tokens accumulated since the last time
+        let elapsed = now.duration_since(bucket.last).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        bucket.last = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / self.refill_per_sec,
+            ))
+        }
+    }
+}
+
+// From<&RateLimitConfig> implementation for RateLimits struct: builds the limiters from the configuration
+impl From<&RateLimitConfig> for RateLimits {
+    fn from(config: &RateLimitConfig) -> Self {
+        Self {
+            ip: RateLimiter::new(&config.ip),
+            user: RateLimiter::new(&config.user),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eviction_keeps_the_recently_used_buckets() {
+        let limiter = RateLimiter::new(&RateConfig {
+            per_minute: 1,
+            burst: 1,
+        });
+
+        // The abuser spends its token, then many other clients fill the limiter
+        assert!(limiter.check(0).is_ok());
+        for key in 1..MAX_BUCKETS as u32 {
+            assert!(limiter.check(key).is_ok());
+        }
+
+        // The abuser keeps sending while the new clients force an eviction
+        assert!(limiter.check(0).is_err());
+        assert!(limiter.check(MAX_BUCKETS as u32).is_ok());
+        assert!(limiter.check(0).is_err());
+
+        let buckets = limiter.buckets.lock().unwrap();
+        assert!(buckets.len() <= MAX_BUCKETS * 9 / 10 + 1);
+        assert!(!buckets.contains_key(&1));
+    }
+}
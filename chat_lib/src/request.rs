@@ -1,9 +1,12 @@
 /// Request module
 // Necessary imports
-use std::{collections::HashMap, hash::Hash};
+use crate::{config::LimitsConfig, response::Status};
+use anyhow::Context;
+use std::{collections::HashMap, fmt, hash::Hash, net::SocketAddr, time::Duration};
 use tokio::{
-    io::{AsyncBufReadExt, AsyncReadExt, BufReader},
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, BufReader, Take},
     net::TcpStream,
+    time::timeout,
 };
 
 #[derive(Debug)]
@@ -20,15 +23,125 @@ pub struct Request {
 
     /// Body if the request, if exists
     pub body: Option<String>,
+
+    /// Address of the client that sent the request
+    pub peer: Option<SocketAddr>,
+}
+
+// Methods for the Request struct
+impl Request {
+    /// Path method returns the uri of the request without the query string
+    pub fn path(&self) -> &str {
+        self.uri
+            .split_once('?')
+            .map_or(self.uri.as_str(), |(path, _)| path)
+    }
+
+    /// Header method returns the value of a header of the request, the name is case insensitive
+    ///
+    /// Args:
+    ///     - name: name of the header
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Query method parses the query string of the uri into an HashMap of decoded keys and values
+    pub fn query(&self) -> HashMap<String, String> {
+        let Some((_, query)) = self.uri.split_once('?') else {
+            return HashMap::new();
+        };
+
+        query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (decode_component(key), decode_component(value))
+            })
+            .collect()
+    }
+}
+
+/// Encode component function percent-encodes a value so that it can be placed in a query string
+///
+/// Args:
+///     - value: value to encode
+pub fn encode_component(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// Decode component function decodes a percent-encoded query string component ('+' is a space)
+///
+/// Args:
+///     - value: value to decode
+pub fn decode_component(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                // Invalid escapes are kept as they are
+                let hex = value
+                    .get(i + 1..i + 3)
+                    .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()));
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
 }
 
-#[derive(Debug, Hash)]
-/// Method enum enumerates the possible methods of an HTTP request (only GET and POST for this app)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Method enum enumerates the possible methods of an HTTP request (only GET, POST and DELETE for this app)
 pub enum Method {
     /// Get HTTP method
     Get,
     /// Post HTTP method
     Post,
+    /// Delete HTTP method
+    Delete,
+}
+
+// Methods for the Method enum
+impl Method {
+    /// As str method returns the name of the method as written in the request
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+            Method::Delete => "DELETE",
+        }
+    }
+}
+
+// Display implementation for Method enum
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 // TryFrom<&str> implementation for Method enum: tries to construct a Method from a &str
@@ -39,73 +152,174 @@ impl TryFrom<&str> for Method {
         match value {
             "GET" => Ok(Method::Get),
             "POST" => Ok(Method::Post),
-            m => Err(anyhow::anyhow!("unsupported method: {m}")),
+            "DELETE" => Ok(Method::Delete),
+            m => Err(
+                RequestError::new(Status::NotImplemented, format!("Unsupported method: {m}"))
+                    .into(),
+            ),
         }
     }
 }
 
+/// RequestError struct representing a request refused while it is read, the client is answered with its status
+/// before the connection is closed
+#[derive(Debug)]
+pub struct RequestError {
+    /// Status of the response
+    pub status: Status,
+
+    /// Reason of the refusal, sent as the body of the response
+    pub reason: String,
+}
+
+// Methods for the RequestError struct
+impl RequestError {
+    /// Constructor method
+    ///
+    /// Args:
+    ///     - status: status of the response
+    ///     - reason: reason of the refusal
+    pub fn new(status: Status, reason: String) -> Self {
+        Self { status, reason }
+    }
+}
+
+// Display implementation for RequestError struct: the reason of the refusal
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.reason)
+    }
+}
+
+// Error implementation for RequestError struct, so that it can travel inside anyhow errors
+impl std::error::Error for RequestError {}
+
 /// Parse request function parses the client request to construct a Request struct
 ///
 /// Args:
 ///     - stream: tcp stream of the connection
-pub async fn parse_request(mut stream: &mut TcpStream) -> Result<Request, anyhow::Error> {
+///     - limits: limits of the request (size of the body and timeouts)
+pub async fn parse_request(
+    mut stream: &mut TcpStream,
+    limits: &LimitsConfig,
+) -> Result<Request, anyhow::Error> {
+    let peer = stream.peer_addr().ok();
     let mut buf_reader = BufReader::new(&mut stream);
 
+    // The whole head of the request must arrive before the deadline, so that slow clients can't hold the connection
+    let (method, uri, headers) = timeout(
+        Duration::from_secs(limits.header_timeout_secs),
+        read_head(&mut buf_reader, limits),
+    )
+    .await
+    .context("Timed out reading the request headers")??;
+
+    // If the content length headers exists the buf reader also reads the body of the request
+    let body = if let Some(cl) = headers.get("Content-Length") {
+        let len: usize = cl.parse().map_err(|_| {
+            RequestError::new(Status::BadRequest, format!("Invalid Content-Length: {cl}"))
+        })?;
+        if len > limits.max_body_bytes {
+            return Err(RequestError::new(
+                Status::PayloadTooLarge,
+                format!(
+                    "Request body too large: {len} bytes, the limit is {} bytes",
+                    limits.max_body_bytes
+                ),
+            )
+            .into());
+        }
+        let mut buf = vec![0; len];
+        timeout(
+            Duration::from_secs(limits.read_timeout_secs),
+            buf_reader.read_exact(&mut buf),
+        )
+        .await
+        .context("Timed out reading the request body")??;
+        let content = String::from_utf8_lossy(&buf).into_owned();
+        Some(content)
+    } else {
+        None
+    };
+
+    let request = Request {
+        method,
+        uri,
+        headers,
+        body,
+        peer,
+    };
+
+    Ok(request)
+}
+
+// Read head function reads the request line and the headers of a request, within the size and count limits
+async fn read_head<R: AsyncBufRead + Unpin>(
+    buf_reader: &mut R,
+    limits: &LimitsConfig,
+) -> Result<(Method, String, HashMap<String, String>), anyhow::Error> {
+    // The head can't grow beyond its limit, whatever the length of its lines
+    let mut head = buf_reader.take(limits.max_header_bytes as u64);
+
     // Buf reader reads the first line of the request
-    let mut first_line = String::new();
-    let _ = buf_reader.read_line(&mut first_line).await?;
+    let first_line = read_line(&mut head).await?;
 
     // The first line is splitted and the parts are trasformend in the Request struct's fields
     let mut parts = first_line.split_whitespace();
 
     let method: Method = parts
         .next()
-        .ok_or(anyhow::anyhow!("Missing method"))
+        .ok_or_else(|| RequestError::new(Status::BadRequest, "Missing method".to_string()).into())
         .and_then(TryInto::try_into)?;
 
     let uri: String = parts
         .next()
-        .ok_or(anyhow::anyhow!("Missing URI"))
-        .map(Into::into)?;
+        .ok_or_else(|| RequestError::new(Status::BadRequest, "Missing URI".to_string()))?
+        .to_string();
 
     let mut headers = HashMap::new();
 
     // The buf reader reads in loop the other lines with the headers
     loop {
-        let mut line = String::new();
-        let _ = buf_reader.read_line(&mut line).await?;
-
-        let line = line.trim_end_matches(&['\r', '\n'][..]);
+        let line = read_line(&mut head).await?;
 
         if line.is_empty() {
             break;
         }
+        if headers.len() >= limits.max_headers {
+            return Err(RequestError::new(
+                Status::RequestHeaderFieldsTooLarge,
+                format!("Too many headers: more than {}", limits.max_headers),
+            )
+            .into());
+        }
 
         // Each header is splitted in key and value and then inserted in an HashMap
-        let mut parts = line.splitn(2, ":");
-        let key = parts.next().unwrap().trim();
-        let value = parts.next().unwrap().trim();
+        let (key, value) = line.split_once(':').ok_or_else(|| {
+            RequestError::new(Status::BadRequest, format!("Invalid header: {line:?}"))
+        })?;
 
-        headers.insert(key.to_string(), value.to_string());
+        headers.insert(key.trim().to_string(), value.trim().to_string());
     }
 
-    // If the content length headers exists the buf reader also reads the body of the request
-    let body = if let Some(cl) = headers.get("Content-Length") {
-        let len = cl.parse()?;
-        let mut buf = vec![0; len];
-        buf_reader.read_exact(&mut buf).await?;
-        let content = String::from_utf8_lossy(&buf).into_owned();
-        Some(content)
-    } else {
-        None
-    };
+    Ok((method, uri, headers))
+}
 
-    let request = Request {
-        method,
-        uri,
-        headers,
-        body,
-    };
+// Read line function reads a line of the head without its line ending, a line cut by the size limit is an error
+async fn read_line<R: AsyncBufRead + Unpin>(head: &mut Take<R>) -> Result<String, anyhow::Error> {
+    let mut line = String::new();
+    head.read_line(&mut line).await?;
 
-    Ok(request)
+    if !line.ends_with('\n') {
+        return Err(match head.limit() {
+            0 => RequestError::new(
+                Status::RequestHeaderFieldsTooLarge,
+                "Request head too large".to_string(),
+            )
+            .into(),
+            _ => anyhow::anyhow!("Connection closed before the end of the request head"),
+        });
+    }
+
+    Ok(line.trim_end_matches(&['\r', '\n'][..]).to_string())
 }
@@ -1,12 +1,18 @@
 /// Response module
 // Necesary imports
 use crate::{
-    messages::{Message, State},
+    admin,
+    context::Context,
+    export::{export, ExportQuery},
+    incoming,
+    messages::Message,
+    moderation::SanctionKind,
     request::{Method, Request},
+    search::{search, SearchQuery},
+    validation::ValidationPolicy,
 };
 use anyhow::anyhow;
-use core::panic;
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt, net::IpAddr};
 use tokio::{
     io::{AsyncBufReadExt, AsyncReadExt, BufReader},
     net::TcpStream,
@@ -24,7 +30,8 @@ pub struct Response {
     pub body: String,
 }
 
-/// Status enum enumerates the possible status code of the response (only 200, 404 and 404 for this app)
+/// Status enum enumerates the possible status code of the response (only 200, 400, 401, 403, 404, 413, 429, 431 and 503 for this app)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Status {
     /// Status Ok: 200
     Ok,
@@ -32,11 +39,92 @@ pub enum Status {
     NotFound,
     /// Status Bad Request: 400
     BadRequest,
+    /// Status Unauthorized: 401
+    Unauthorized,
+    /// Status Forbidden: 403
+    Forbidden,
+    /// Status Payload Too Large: 413
+    PayloadTooLarge,
+    /// Status Too Many Requests: 429
+    TooManyRequests,
+    /// Status Request Header Fields Too Large: 431
+    RequestHeaderFieldsTooLarge,
+    /// Status Internal Server Error: 500
+    InternalServerError,
+    /// Status Not Implemented: 501
+    NotImplemented,
+    /// Status Service Unavailable: 503
+    ServiceUnavailable,
 }
 
-// ToString implementation for Response struct: format the Response struct in the right way to be sent over the tcp stream
-impl ToString for Response {
-    fn to_string(&self) -> String {
+// Methods for the Response struct
+impl Response {
+    /// Constructor method, the headers are filled from the content type and the body
+    ///
+    /// Args:
+    ///     - status: status of the response
+    ///     - content_type: content type of the body
+    ///     - body: body of the response
+    pub fn new(status: Status, content_type: &str, body: String) -> Self {
+        let mut headers = HashMap::new();
+
+        headers.insert("Content-Type".to_string(), content_type.to_string());
+        headers.insert("Content-Length".to_string(), body.len().to_string());
+
+        Self {
+            status,
+            headers,
+            body,
+        }
+    }
+
+    /// Header method adds a header to the response
+    ///
+    /// Args:
+    ///     - key: name of the header
+    ///     - value: value of the header
+    pub fn header(mut self, key: &str, value: &str) -> Self {
+        self.headers.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Get header method returns the value of a header of the response, if exists
+    ///
+    /// Args:
+    ///     - key: name of the header
+    pub fn get_header(&self, key: &str) -> Option<&str> {
+        self.headers.get(key).map(String::as_str)
+    }
+
+    /// Status method returns the status of the response
+    pub fn status(&self) -> Status {
+        self.status
+    }
+}
+
+// Methods for the Status enum
+impl Status {
+    /// Code method returns the numeric status code
+    pub fn code(&self) -> u16 {
+        match self {
+            Self::Ok => 200,
+            Self::NotFound => 404,
+            Self::BadRequest => 400,
+            Self::Unauthorized => 401,
+            Self::Forbidden => 403,
+            Self::PayloadTooLarge => 413,
+            Self::TooManyRequests => 429,
+            Self::RequestHeaderFieldsTooLarge => 431,
+            Self::InternalServerError => 500,
+            Self::NotImplemented => 501,
+            Self::ServiceUnavailable => 503,
+        }
+    }
+}
+
+// Display implementation for Response struct: format the Response struct in the right way to be sent over the tcp stream
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let status_line = self.status.to_string();
         let headers = self
             .headers
@@ -45,20 +133,28 @@ impl ToString for Response {
             .collect::<Vec<_>>()
             .join("\r\n");
 
-        format!("{status_line}\r\n{headers}\r\n\r\n{}", self.body)
+        write!(f, "{status_line}\r\n{headers}\r\n\r\n{}", self.body)
     }
 }
 
-// ToString implementation for Status Enum: format the status into the correct status line of the response (including http version)
-impl ToString for Status {
-    fn to_string(&self) -> String {
+// Display implementation for Status Enum: format the status into the correct status line of the response (including http version)
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let status_line = match self {
             Self::Ok => "HTTP/1.1 200 OK",
             Self::NotFound => "HTTP/1.1 404 NOT FOUND",
             Self::BadRequest => "HTTP/1.1 400 BAD REQUEST",
+            Self::Unauthorized => "HTTP/1.1 401 UNAUTHORIZED",
+            Self::Forbidden => "HTTP/1.1 403 FORBIDDEN",
+            Self::PayloadTooLarge => "HTTP/1.1 413 PAYLOAD TOO LARGE",
+            Self::TooManyRequests => "HTTP/1.1 429 TOO MANY REQUESTS",
+            Self::RequestHeaderFieldsTooLarge => "HTTP/1.1 431 REQUEST HEADER FIELDS TOO LARGE",
+            Self::InternalServerError => "HTTP/1.1 500 INTERNAL SERVER ERROR",
+            Self::NotImplemented => "HTTP/1.1 501 NOT IMPLEMENTED",
+            Self::ServiceUnavailable => "HTTP/1.1 503 SERVICE UNAVAILABLE",
         };
 
-        status_line.to_string()
+        f.write_str(status_line)
     }
 }
 
@@ -66,35 +162,163 @@ impl ToString for Status {
 ///
 /// Args:
 ///     - req: request from the client
-///     - state: state of the server with all the messages
-pub async fn get_response(req: Request, state: State) -> Result<Response, anyhow::Error> {
+///     - ctx: context of the server with the messages and the configuration
+pub async fn get_response(req: Request, ctx: &Context) -> Result<Response, anyhow::Error> {
+    let state = &ctx.state;
+
+    // The health endpoints are answered even while shutting down, so that the supervisor sees it
+    match (req.method, req.path()) {
+        (Method::Get, "/healthz") => {
+            // The server is alive as long as it answers
+            return Ok(Response::new(
+                Status::Ok,
+                "application/json",
+                r#"{"status":"ok"}"#.to_string(),
+            ));
+        }
+        (Method::Get, "/readyz") => return Ok(readiness(ctx).await),
+        _ => {}
+    }
+
+    // The requests received while shutting down are refused, the clients will try again later
+    if ctx.shutdown.is_triggered() {
+        return Ok(Response::new(
+            Status::ServiceUnavailable,
+            "text/plain",
+            "Server is shutting down".to_string(),
+        ));
+    }
+
+    // The admin api has its own routes
+    if req.path().starts_with("/admin/") {
+        return admin::get_response(&req, ctx).await;
+    }
+
+    // The incoming webhooks are authenticated by their token
+    if req.path().starts_with("/hooks/") {
+        return incoming::get_response(&req, ctx).await;
+    }
+
+    // Banned users and addresses can't use the chat
+    let query = req.query();
+    let peer = req.peer.map(|peer| peer.ip());
+    let reader = match req.path() {
+        "/messages/search" | "/export" => query.get("reader"),
+        _ => query.get("user"),
+    };
+    if let Err(e) = ctx
+        .moderation
+        .check(SanctionKind::Ban, reader.map(String::as_str), peer)
+    {
+        return Ok(Response::new(
+            Status::Forbidden,
+            "text/plain",
+            e.to_string(),
+        ));
+    }
+
     // Check the request method and uri in order to return the correct response
-    let (status, content_type, body) = match (req.method, req.uri.as_str()) {
+    let (status, content_type, body) = match (req.method, req.path()) {
         (Method::Get, "/messages") => {
             // Get method + /messages endpoint means that the client is asking for the messages
             let msgs = state.lock().await;
 
+            // The optional room query parameter restricts the messages to a room,
+            // direct messages are only returned to their sender and recipient passed in the user query parameter
+            // (the username is not authenticated, they are not confidential)
+            let room = query.get("room");
+            let user = query.get("user");
+            let msgs = msgs
+                .iter()
+                .filter(|msg| room.is_none_or(|room| &msg.room == room))
+                .filter(|msg| match user {
+                    Some(user) => msg.is_visible_to(user),
+                    None => msg.to.is_none(),
+                })
+                .filter_map(|msg| ctx.hooks.deliver(msg, user.map(String::as_str)))
+                .collect::<Vec<_>>();
+
             (
                 Status::Ok,
                 "application/json".to_string(),
-                serde_json::to_string(&*msgs)?, // the body of the response will be the messages in json format
+                serde_json::to_string(&msgs)?, // the body of the response will be the messages in json format
+            )
+        }
+        (Method::Get, "/messages/search") => {
+            // Get method + /messages/search endpoint means that the client is searching the messages
+            let msgs = state.lock().await;
+
+            // An invalid query is a bad request, the error is sent back to the client
+            match SearchQuery::try_from(&query).and_then(|query| search(&msgs, &query)) {
+                Ok(results) => {
+                    let results = results
+                        .iter()
+                        .filter_map(|msg| ctx.hooks.deliver(msg, reader.map(String::as_str)))
+                        .collect::<Vec<_>>();
+                    (
+                        Status::Ok,
+                        "application/json".to_string(),
+                        serde_json::to_string(&results)?,
+                    )
+                }
+                Err(e) => (Status::BadRequest, "text/plain".to_string(), e.to_string()),
+            }
+        }
+        (Method::Get, "/export") => {
+            // Get method + /export endpoint means that the client is downloading a transcript of the history
+            let msgs = state.lock().await;
+
+            match ExportQuery::try_from(&query) {
+                Ok(export_query) => {
+                    let msgs = msgs
+                        .iter()
+                        .filter(|msg| export_query.matches(msg))
+                        .filter_map(|msg| ctx.hooks.deliver(msg, reader.map(String::as_str)))
+                        .collect::<Vec<_>>();
+                    let body = export(msgs.iter().map(AsRef::as_ref), &export_query)?;
+                    let format = export_query.format;
+
+                    // The transcript is offered as a file to the browsers
+                    let disposition =
+                        format!("attachment; filename=\"transcript.{}\"", format.extension());
+                    return Ok(Response::new(Status::Ok, format.content_type(), body)
+                        .header("Content-Disposition", &disposition));
+                }
+                Err(e) => (Status::BadRequest, "text/plain".to_string(), e.to_string()),
+            }
+        }
+        (Method::Get, "/policy") => {
+            // Get method + /policy endpoint means that the client is asking for the rules the messages must follow
+            let policy: &ValidationPolicy = &ctx.config.validation;
+            (
+                Status::Ok,
+                "application/json".to_string(),
+                serde_json::to_string(policy)?,
+            )
+        }
+        (Method::Get, "/metrics") => {
+            // Get method + /metrics endpoint means that a monitoring system is scraping the metrics of the server
+            let msgs = state.lock().await;
+            (
+                Status::Ok,
+                "text/plain; version=0.0.4".to_string(),
+                ctx.metrics.render(&msgs),
             )
         }
         (Method::Post, "/messages") => {
             // Post method + /messages endpoint means that the client is trying to send a new message
 
             // Check if the response has a body
-            if req.body.is_some() {
-                // Read the message from the body and update the server state
-                let body_content: String = req.body.unwrap();
-                let msg = serde_json::from_str::<Message>(body_content.as_str())?;
-                let mut msgs = state.lock().await;
-                msgs.push(msg);
-                (
-                    Status::Ok,
-                    "application/json".to_string(),
-                    r#"{"status":"ok"}"#.to_string(),
-                )
+            if let Some(body_content) = req.body {
+                // Read the message from the body, the errors are sent back to the client
+                match serde_json::from_str::<Message>(body_content.as_str()) {
+                    Ok(msg) => return post_message(ctx, msg, peer).await,
+                    Err(e) => (
+                        Status::BadRequest,
+                        "text/plain".to_string(),
+                        format!("Invalid message: {e}"),
+                    ),
+                }
             } else {
                 // If there's no message return a bad request status code
                 (
@@ -111,21 +335,117 @@ pub async fn get_response(req: Request, state: State) -> Result<Response, anyhow
         ),
     };
 
-    // Construct the headers
-    let length = body.len();
+    Ok(Response::new(status, &content_type, body))
+}
 
-    let mut headers = HashMap::new();
+/// Readiness function checks if the server can serve the requests: the storage must be available
+/// and the server must not be shutting down (503 otherwise, with the failed checks)
+///
+/// Args:
+///     - ctx: context of the server
+pub async fn readiness(ctx: &Context) -> Response {
+    let storage = match &ctx.store {
+        Some(store) => store.check().await.map_err(|e| format!("{e:#}")),
+        None => Ok(()),
+    };
+    let shutdown = match ctx.shutdown.is_triggered() {
+        true => Err("shutting down".to_string()),
+        false => Ok(()),
+    };
 
-    headers.insert("Content-Type".to_string(), content_type);
-    headers.insert("Content-Length".to_string(), length.to_string());
+    let ready = storage.is_ok() && shutdown.is_ok();
+    let checks: HashMap<&str, String> = [("storage", storage), ("shutdown", shutdown)]
+        .into_iter()
+        .map(|(name, check)| (name, check.err().unwrap_or_else(|| "ok".to_string())))
+        .collect();
+    let body = serde_json::json!({
+        "status": if ready { "ready" } else { "not ready" },
+        "checks": checks,
+    });
 
-    let response = Response {
-        status,
-        headers,
-        body,
-    };
+    Response::new(
+        if ready {
+            Status::Ok
+        } else {
+            Status::ServiceUnavailable
+        },
+        "application/json",
+        body.to_string(),
+    )
+}
 
-    Ok(response)
+/// Post message function checks a new message (validation, sanctions, rate limits and hooks) and publishes it,
+/// the response contains the id of the message or the reason it was refused
+///
+/// Args:
+///     - ctx: context of the server
+///     - msg: new message
+///     - peer: address of the client that sent the message, if known
+pub async fn post_message(
+    ctx: &Context,
+    mut msg: Message,
+    peer: Option<IpAddr>,
+) -> Result<Response, anyhow::Error> {
+    // The annotations are added by the hooks of the server only, the ones sent by the client are dropped
+    msg.annotations.clear();
+
+    // The message is checked against the validation policy
+    if let Err(e) = ctx.config.validation.validate(&mut msg) {
+        return Ok(Response::new(
+            Status::BadRequest,
+            "text/plain",
+            e.to_string(),
+        ));
+    }
+
+    // Banned and muted users can't send messages
+    let sanctioned = ctx
+        .moderation
+        .check(SanctionKind::Ban, Some(&msg.user), peer)
+        .and_then(|_| {
+            ctx.moderation
+                .check(SanctionKind::Mute, Some(&msg.user), peer)
+        });
+    if let Err(e) = sanctioned {
+        return Ok(Response::new(
+            Status::Forbidden,
+            "text/plain",
+            e.to_string(),
+        ));
+    }
+
+    // The messages over the rate limit of the client address or of the user are refused
+    let limited = peer
+        .map_or(Ok(()), |peer| ctx.rate_limits.ip.check(peer))
+        .and_then(|_| ctx.rate_limits.user.check(msg.user.clone()));
+    if let Err(retry_after) = limited {
+        let secs = retry_after.as_secs_f64().ceil() as u64;
+        return Ok(Response::new(
+            Status::TooManyRequests,
+            "text/plain",
+            format!("Slow down: too many messages, retry in {secs}s"),
+        )
+        .header("Retry-After", &secs.to_string()));
+    }
+
+    // The hooks (like the content filters) can transform the message or reject it
+    if let Err(e) = ctx.hooks.on_receive(&mut msg) {
+        return Ok(Response::new(
+            Status::BadRequest,
+            "text/plain",
+            e.to_string(),
+        ));
+    }
+
+    // Stamp the message with an id and the server time and update the server state
+    let id = ctx.publish(msg).await?;
+
+    // The id is sent back so that the client can recognize its message
+    Ok(Response::new(
+        Status::Ok,
+        "application/json",
+        format!(r#"{{"status":"ok","id":{id}}}"#),
+    ))
 }
 
 /// Parse response function construct a Response struct from the HTTP response
@@ -152,7 +472,15 @@ pub async fn parse_response(mut stream: &mut TcpStream) -> Result<Response, anyh
         200 => Status::Ok,
         404 => Status::NotFound,
         400 => Status::BadRequest,
-        _ => panic!("Invalid status code"),
+        401 => Status::Unauthorized,
+        403 => Status::Forbidden,
+        413 => Status::PayloadTooLarge,
+        429 => Status::TooManyRequests,
+        431 => Status::RequestHeaderFieldsTooLarge,
+        500 => Status::InternalServerError,
+        501 => Status::NotImplemented,
+        503 => Status::ServiceUnavailable,
+        code => return Err(anyhow!("Invalid status code: {code}")),
     };
 
     let mut headers = HashMap::new();
@@ -169,11 +497,11 @@ pub async fn parse_response(mut stream: &mut TcpStream) -> Result<Response, anyh
         }
 
         // Each header is divided into key and value and then inserted into an HashMap
-        let mut parts = line.splitn(2, ":");
-        let key = parts.next().unwrap().trim();
-        let value = parts.next().unwrap().trim();
+        let (key, value) = line
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Invalid header line: {line}"))?;
 
-        headers.insert(key.to_string(), value.to_string());
+        headers.insert(key.trim().to_string(), value.trim().to_string());
     }
 
     let body;
@@ -0,0 +1,176 @@
+/// Retention module: bounds the history kept by the server, dropping or archiving the oldest messages
+// Necessary imports
+use crate::{config::RetentionConfig, context::Context, messages::unix_now, messages::Message};
+use std::{mem, path::Path, sync::Arc, time::Duration};
+use tokio::{
+    fs::OpenOptions,
+    io::AsyncWriteExt,
+    select,
+    time::{interval, MissedTickBehavior},
+};
+use tracing::{info, warn};
+
+/// Retention task function applies the retention policy periodically until the server shuts down
+///
+/// Args:
+///     - ctx: context of the server with the messages and the configuration
+pub async fn retention_task(ctx: Arc<Context>) {
+    let config = &ctx.config.retention;
+    let mut ticks = interval(Duration::from_secs(config.interval_secs.max(1)));
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        select! {
+            _ = ticks.tick() => {}
+            _ = ctx.shutdown.wait() => return,
+        }
+
+        match enforce(&ctx).await {
+            Ok(0) => {}
+            Ok(removed) => info!("Retention removed {removed} messages"),
+            Err(e) => warn!(?e, "failed to apply the retention policy"),
+        }
+    }
+}
+
+/// Enforce function removes the messages beyond the retention limits, archiving them if an archive is configured,
+/// and returns how many were removed
+///
+/// Args:
+///     - ctx: context of the server with the messages and the configuration
+pub async fn enforce(ctx: &Context) -> Result<usize, anyhow::Error> {
+    let config = &ctx.config.retention;
+
+    // The state stays locked until the store is rewritten, so that no message is appended in the meantime
+    let mut msgs = ctx.state.lock().await;
+    let expired = expired(&msgs, config, unix_now());
+    if !expired.contains(&true) {
+        return Ok(0);
+    }
+
+    // The history is only replaced once the removed messages are archived and the store is rewritten,
+    // so that a failure (e.g. a full disk) loses nothing: the next run tries again
+    let (removed, kept): (Vec<_>, Vec<_>) =
+        msgs.iter().zip(expired).partition(|(_, expired)| *expired);
+    let removed: Vec<Message> = removed.into_iter().map(|(msg, _)| msg.clone()).collect();
+    let kept: Vec<Message> = kept.into_iter().map(|(msg, _)| msg.clone()).collect();
+
+    if let Some(path) = &config.archive {
+        archive(path, &removed).await?;
+    }
+    if let Some(store) = &ctx.store {
+        store.rewrite(&kept).await?;
+    }
+    *msgs = kept;
+
+    Ok(removed.len())
+}
+
+// Expired function marks the messages beyond the retention limits (0 disables a limit). The count and the size
+// drop the oldest messages by id, the age is checked on every message since the imported ones can be out of order
+fn expired(msgs: &[Message], config: &RetentionConfig, now: u64) -> Vec<bool> {
+    // Oldest messages over the maximum count
+    let by_count = match config.max_messages {
+        0 => 0,
+        max => msgs.len().saturating_sub(max),
+    };
+
+    // Oldest messages that don't fit in the maximum size, counted from the newest one
+    let by_bytes = match config.max_bytes {
+        0 => 0,
+        max => {
+            let mut total = 0;
+            msgs.iter()
+                .rposition(|msg| {
+                    total += size(msg);
+                    total > max
+                })
+                .map_or(0, |position| position + 1)
+        }
+    };
+
+    // Messages older than the maximum age
+    let oldest = match config.max_age_secs {
+        0 => None,
+        max => Some(now.saturating_sub(max)),
+    };
+
+    let cut = by_count.max(by_bytes);
+    msgs.iter()
+        .enumerate()
+        .map(|(i, msg)| i < cut || oldest.is_some_and(|oldest| msg.timestamp < oldest))
+        .collect()
+}
+
+/// Size function estimates the memory used by a message
+///
+/// Args:
+///     - msg: message to measure
+pub fn size(msg: &Message) -> usize {
+    mem::size_of::<Message>()
+        + msg.user.len()
+        + msg.message.len()
+        + msg.room.len()
+        + msg.to.as_ref().map_or(0, String::len)
+        + msg
+            .annotations
+            .iter()
+            .map(|(key, value)| key.len() + value.len())
+            .sum::<usize>()
+}
+
+// Archive function appends the removed messages to the archive file (JSON Lines, like the store)
+async fn archive(path: &Path, msgs: &[Message]) -> Result<(), anyhow::Error> {
+    let mut lines = String::new();
+    for msg in msgs {
+        lines.push_str(&serde_json::to_string(msg)?);
+        lines.push('\n');
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    file.write_all(lines.as_bytes()).await?;
+    file.flush().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Message function builds a message with an id and a timestamp
+    fn message(id: u64, timestamp: u64) -> Message {
+        let mut message = Message::new("alice", "hello", "general");
+        message.id = id;
+        message.timestamp = timestamp;
+        message
+    }
+
+    #[test]
+    fn expired_drops_the_oldest_messages_over_the_count() {
+        let config = RetentionConfig {
+            max_messages: 2,
+            ..Default::default()
+        };
+        let msgs = [message(1, 10), message(2, 20), message(3, 30)];
+
+        assert_eq!(expired(&msgs, &config, 100), vec![true, false, false]);
+    }
+
+    #[test]
+    fn expired_checks_the_age_of_every_message() {
+        let config = RetentionConfig {
+            max_messages: 0,
+            max_age_secs: 50,
+            ..Default::default()
+        };
+        // An imported message can be older than the ones before it
+        let msgs = [message(1, 60), message(2, 10), message(3, 90)];
+
+        assert_eq!(expired(&msgs, &config, 100), vec![false, true, false]);
+    }
+}
@@ -0,0 +1,86 @@
+/// Search module
+// Necessary imports
+use crate::messages::Message;
+use anyhow::anyhow;
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+
+/// Maximum number of messages returned by a search
+const MAX_RESULTS: usize = 100;
+
+/// SearchQuery struct representing the parameters of a search in the messages
+#[derive(Debug)]
+pub struct SearchQuery {
+    /// Text (or regular expression) to look for, case-insensitive
+    pub text: String,
+
+    /// Only the messages sent by this user are searched, if given
+    pub user: Option<String>,
+
+    /// Only the messages of this room are searched, if given
+    pub room: Option<String>,
+
+    /// Username of the reader, direct messages are searched only if they are addressed to or sent by them
+    pub reader: Option<String>,
+
+    /// Whether the text is a regular expression
+    pub regex: bool,
+}
+
+// TryFrom<&HashMap<String, String>> implementation for SearchQuery struct: constructs the query from the query string parameters
+impl TryFrom<&HashMap<String, String>> for SearchQuery {
+    type Error = anyhow::Error;
+
+    fn try_from(params: &HashMap<String, String>) -> Result<Self, Self::Error> {
+        let text = params
+            .get("q")
+            .filter(|q| !q.is_empty())
+            .ok_or(anyhow!("Missing search text (q parameter)"))?;
+
+        Ok(SearchQuery {
+            text: text.clone(),
+            user: params.get("user").cloned(),
+            room: params.get("room").cloned(),
+            reader: params.get("reader").cloned(),
+            regex: params
+                .get("regex")
+                .is_some_and(|regex| regex == "1" || regex == "true"),
+        })
+    }
+}
+
+/// Search function returns the messages matching the query, from the oldest to the newest
+///
+/// Args:
+///     - messages: messages to search
+///     - query: parameters of the search
+pub fn search(messages: &[Message], query: &SearchQuery) -> Result<Vec<Message>, anyhow::Error> {
+    // A plain text search is a case-insensitive regex of the escaped text
+    let pattern = if query.regex {
+        query.text.clone()
+    } else {
+        regex::escape(&query.text)
+    };
+    let regex: Regex = RegexBuilder::new(&pattern)
+        .case_insensitive(true)
+        .size_limit(1 << 20)
+        .build()
+        .map_err(|e| anyhow!("Invalid regular expression: {e}"))?;
+
+    let mut results: Vec<Message> = messages
+        .iter()
+        .rev()
+        .filter(|msg| query.user.as_ref().is_none_or(|user| &msg.user == user))
+        .filter(|msg| query.room.as_ref().is_none_or(|room| &msg.room == room))
+        .filter(|msg| match &query.reader {
+            Some(reader) => msg.is_visible_to(reader),
+            None => msg.to.is_none(),
+        })
+        .filter(|msg| regex.is_match(&msg.message))
+        .take(MAX_RESULTS)
+        .cloned()
+        .collect();
+    results.reverse();
+
+    Ok(results)
+}
@@ -0,0 +1,59 @@
+/// Shutdown module: graceful shutdown of the server
+// Necessary imports
+use tokio::sync::watch;
+
+/// Shutdown struct shared by the connections to know when the server is shutting down
+#[derive(Debug)]
+pub struct Shutdown {
+    /// Sender of the shutdown state, true once the shutdown started
+    sender: watch::Sender<bool>,
+}
+
+// Methods for the Shutdown struct
+impl Shutdown {
+    /// Trigger method starts the shutdown
+    pub fn trigger(&self) {
+        self.sender.send_replace(true);
+    }
+
+    /// Is triggered method checks if the shutdown started
+    pub fn is_triggered(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    /// Wait method returns when the shutdown starts
+    pub async fn wait(&self) {
+        let mut receiver = self.sender.subscribe();
+        let _ = receiver.wait_for(|triggered| *triggered).await;
+    }
+}
+
+// Default implementation for Shutdown struct
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self {
+            sender: watch::Sender::new(false),
+        }
+    }
+}
+
+/// Signal function waits for a termination signal (SIGINT or SIGTERM) and returns its name
+#[cfg(unix)]
+pub async fn signal() -> Result<&'static str, anyhow::Error> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut terminate = signal(SignalKind::terminate())?;
+
+    tokio::select! {
+        _ = interrupt.recv() => Ok("SIGINT"),
+        _ = terminate.recv() => Ok("SIGTERM"),
+    }
+}
+
+/// Signal function waits for a termination signal (Ctrl-C) and returns its name
+#[cfg(not(unix))]
+pub async fn signal() -> Result<&'static str, anyhow::Error> {
+    tokio::signal::ctrl_c().await?;
+    Ok("Ctrl-C")
+}
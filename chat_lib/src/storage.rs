@@ -0,0 +1,162 @@
+/// Storage module
+// Necessary imports
+use crate::messages::Message;
+use anyhow::Context;
+use std::path::{Path, PathBuf};
+use tokio::{
+    fs::{self, File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::Mutex,
+};
+
+/// Store struct representing the persistent storage of the messages: a JSON Lines file with a message per line
+#[derive(Debug)]
+pub struct Store {
+    /// Path of the file
+    path: PathBuf,
+
+    /// File opened in append mode
+    file: Mutex<File>,
+}
+
+// Methods for the Store struct
+impl Store {
+    /// Open method opens (or creates) the file of the store and loads the saved messages,
+    /// the file is locked until the store is dropped so that two processes can't write it at once
+    ///
+    /// Args:
+    ///     - path: path of the file
+    pub async fn open(path: &Path) -> Result<(Self, Vec<Message>), anyhow::Error> {
+        let messages = match fs::read_to_string(path).await {
+            Ok(content) => content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(serde_json::from_str::<Message>)
+                .collect::<Result<Vec<_>, _>>()
+                .with_context(|| format!("Corrupted store {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e.into()),
+        };
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .with_context(|| format!("Failed to open store {}", path.display()))?;
+        let file = lock(file, path).await?;
+
+        let store = Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+        };
+
+        Ok((store, messages))
+    }
+
+    /// Append method saves a new message at the end of the file
+    ///
+    /// Args:
+    ///     - message: message to save
+    pub async fn append(&self, message: &Message) -> Result<(), anyhow::Error> {
+        let mut line = serde_json::to_string(message)?;
+        line.push('\n');
+
+        let mut file = self.file.lock().await;
+        file.write_all(line.as_bytes()).await?;
+        file.flush().await?;
+
+        Ok(())
+    }
+
+    /// Rewrite method replaces the content of the file with the given messages, used to drop the old ones
+    ///
+    /// Args:
+    ///     - messages: messages to keep
+    pub async fn rewrite(&self, messages: &[Message]) -> Result<(), anyhow::Error> {
+        let mut content = String::new();
+        for message in messages {
+            content.push_str(&serde_json::to_string(message)?);
+            content.push('\n');
+        }
+
+        // The new content is written aside and then moved over the file, so that a crash can't leave it half written
+        let mut file = self.file.lock().await;
+        let tmp = PathBuf::from(format!("{}.tmp", self.path.display()));
+        fs::write(&tmp, content)
+            .await
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        fs::rename(&tmp, &self.path)
+            .await
+            .with_context(|| format!("Failed to replace store {}", self.path.display()))?;
+
+        let reopened = OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .await
+            .with_context(|| format!("Failed to open store {}", self.path.display()))?;
+        *file = lock(reopened, &self.path).await?;
+
+        Ok(())
+    }
+
+    /// Flush method makes sure that all the saved messages reached the disk
+    pub async fn flush(&self) -> Result<(), anyhow::Error> {
+        let mut file = self.file.lock().await;
+        file.flush().await?;
+        file.sync_all().await?;
+
+        Ok(())
+    }
+
+    /// Check method checks that the file of the store is still there and writable
+    pub async fn check(&self) -> Result<(), anyhow::Error> {
+        let metadata = fs::metadata(&self.path)
+            .await
+            .with_context(|| format!("Store {} is not available", self.path.display()))?;
+        if metadata.permissions().readonly() {
+            return Err(anyhow::anyhow!(
+                "Store {} is read only",
+                self.path.display()
+            ));
+        }
+
+        // The messages would be appended to a deleted file if it was replaced behind the server
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+
+            let opened = self.file.lock().await.metadata().await?;
+            if (opened.dev(), opened.ino()) != (metadata.dev(), metadata.ino()) {
+                return Err(anyhow::anyhow!(
+                    "Store {} was replaced, the server must be restarted",
+                    self.path.display()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Path method returns the path of the file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+// Lock function takes an exclusive lock on the file of the store, it fails if another process (a running server
+// or an import) holds it
+async fn lock(file: File, path: &Path) -> Result<File, anyhow::Error> {
+    let file = file.into_std().await;
+    file.try_lock().map_err(|e| match e {
+        std::fs::TryLockError::WouldBlock => anyhow::anyhow!(
+            "Store {} is in use by another process, is the server running?",
+            path.display()
+        ),
+        std::fs::TryLockError::Error(e) => {
+            anyhow::Error::new(e).context(format!("Failed to lock store {}", path.display()))
+        }
+    })?;
+
+    Ok(File::from_std(file))
+}
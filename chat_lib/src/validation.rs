@@ -0,0 +1,178 @@
+/// Validation module: rules the messages must follow, shared by the server and the clients
+// Necessary imports
+use crate::messages::Message;
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+/// ValidationPolicy struct containing the limits of the messages, of the usernames and of the room names
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ValidationPolicy {
+    /// Maximum number of characters of a message
+    pub max_message_len: usize,
+
+    /// Maximum number of characters of usernames and room names
+    pub max_name_len: usize,
+
+    /// Characters allowed in usernames and room names besides letters and digits
+    pub name_chars: String,
+}
+
+// Default implementation for ValidationPolicy struct
+impl Default for ValidationPolicy {
+    fn default() -> Self {
+        Self {
+            max_message_len: 2000,
+            max_name_len: 32,
+            name_chars: "_-.".to_string(),
+        }
+    }
+}
+
+// Methods for the ValidationPolicy struct
+impl ValidationPolicy {
+    /// Validate method checks a message, the control characters of its text are removed
+    ///
+    /// Args:
+    ///     - message: message to check
+    pub fn validate(&self, message: &mut Message) -> Result<(), anyhow::Error> {
+        self.validate_name("Username", &message.user)?;
+        self.validate_name("Room name", &message.room)?;
+        if let Some(to) = &message.to {
+            self.validate_name("Recipient", to)?;
+        }
+        message.message = self.sanitize_text(&message.message)?;
+
+        Ok(())
+    }
+
+    /// Validate name method checks a username or a room name
+    ///
+    /// Args:
+    ///     - what: what the name is, used in the error
+    ///     - name: name to check
+    pub fn validate_name(&self, what: &str, name: &str) -> Result<(), anyhow::Error> {
+        if name.is_empty() {
+            return Err(anyhow!("{what} must not be empty"));
+        }
+        if name.chars().count() > self.max_name_len {
+            return Err(anyhow!(
+                "{what} is too long (max {} characters)",
+                self.max_name_len
+            ));
+        }
+        if let Some(c) = name
+            .chars()
+            .find(|c| !c.is_alphanumeric() && !self.name_chars.contains(*c))
+        {
+            return Err(anyhow!(
+                "{what} contains an invalid character {c:?} (allowed: letters, digits and {:?})",
+                self.name_chars
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Sanitize text method removes the control characters from the text of a message (tabs become spaces)
+    /// and checks that what is left is neither empty nor too long
+    ///
+    /// Args:
+    ///     - text: text of the message
+    pub fn sanitize_text(&self, text: &str) -> Result<String, anyhow::Error> {
+        let text: String = text
+            .chars()
+            .map(|c| if c == '\t' { ' ' } else { c })
+            .filter(|c| !c.is_control())
+            .collect();
+        let text = text.trim();
+
+        if text.is_empty() {
+            return Err(anyhow!("Message must not be empty"));
+        }
+        if text.chars().count() > self.max_message_len {
+            return Err(anyhow!(
+                "Message is too long (max {} characters)",
+                self.max_message_len
+            ));
+        }
+
+        Ok(text.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Policy function builds a policy with small limits
+    fn policy() -> ValidationPolicy {
+        ValidationPolicy {
+            max_message_len: 5,
+            max_name_len: 4,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn validate_name_checks_the_length_and_the_characters() {
+        let policy = policy();
+
+        assert!(policy.validate_name("Username", "a_b.").is_ok());
+        assert!(policy.validate_name("Username", "èé").is_ok());
+        assert_eq!(
+            policy
+                .validate_name("Username", "")
+                .unwrap_err()
+                .to_string(),
+            "Username must not be empty"
+        );
+        assert_eq!(
+            policy
+                .validate_name("Room name", "abcde")
+                .unwrap_err()
+                .to_string(),
+            "Room name is too long (max 4 characters)"
+        );
+        assert!(policy.validate_name("Username", "a b").is_err());
+        assert!(policy.validate_name("Username", "a/b").is_err());
+    }
+
+    #[test]
+    fn sanitize_text_removes_the_control_characters() {
+        let policy = policy();
+
+        assert_eq!(policy.sanitize_text("a\tb\u{7}").unwrap(), "a b");
+        assert_eq!(policy.sanitize_text("  hi\r\n").unwrap(), "hi");
+        assert!(policy.sanitize_text(" \u{1b}\t").is_err());
+    }
+
+    #[test]
+    fn sanitize_text_counts_characters_not_bytes() {
+        let policy = policy();
+
+        assert_eq!(policy.sanitize_text("ééééé").unwrap(), "ééééé");
+        assert_eq!(
+            policy.sanitize_text("abcdef").unwrap_err().to_string(),
+            "Message is too long (max 5 characters)"
+        );
+    }
+
+    #[test]
+    fn validate_checks_every_field_and_sanitizes_the_text() {
+        let policy = policy();
+
+        let mut message = Message::new("bob", "hi\u{0}", "gen");
+        policy.validate(&mut message).unwrap();
+        assert_eq!(message.message, "hi");
+
+        message.to = Some("too long".to_string());
+        assert_eq!(
+            policy.validate(&mut message).unwrap_err().to_string(),
+            "Recipient is too long (max 4 characters)"
+        );
+
+        let mut message = Message::new("bob", "hi", "general");
+        assert!(policy.validate(&mut message).is_err());
+    }
+}
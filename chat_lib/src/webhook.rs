@@ -0,0 +1,339 @@
+/// Webhook module: outgoing webhooks the new messages are posted to
+// Necessary imports
+use crate::{
+    client::{resolve, Backoff},
+    config::WebhookConfig,
+    hooks::MessageHook,
+    messages::Message,
+};
+use anyhow::{anyhow, Context as _};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::{str::FromStr, time::Duration};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+    sync::mpsc,
+    time::{sleep, timeout},
+};
+use tracing::{debug, warn};
+
+/// Messages waiting to be delivered to a webhook, the new ones are dropped when the queue is full
+const QUEUE_SIZE: usize = 1024;
+
+/// Header of the requests containing the HMAC-SHA256 signature of the body ("sha256=<hex>")
+pub static SIGNATURE_HEADER: &str = "X-Chat-Signature";
+
+/// Header of the requests containing the id of the message
+pub static MESSAGE_ID_HEADER: &str = "X-Chat-Message-Id";
+
+/// Endpoint struct representing the url of a webhook
+#[derive(Debug, Clone)]
+struct Endpoint {
+    /// Host and port as written in the url, sent in the Host header
+    authority: String,
+
+    /// Hostname or ip address
+    host: String,
+
+    /// Port, 80 if not in the url
+    port: u16,
+
+    /// Path and query string
+    path: String,
+}
+
+/// Webhook struct representing an outgoing webhook and the queue of its delivery task
+#[derive(Debug)]
+pub struct Webhook {
+    /// Settings of the webhook
+    config: WebhookConfig,
+
+    /// Queue of the messages to deliver
+    queue: mpsc::Sender<Message>,
+}
+
+/// Webhooks struct containing the outgoing webhooks, registered among the hooks of the server
+#[derive(Debug, Default)]
+pub struct Webhooks {
+    /// Configured webhooks
+    webhooks: Vec<Webhook>,
+}
+
+// FromStr implementation for Endpoint struct: parses an http url
+impl FromStr for Endpoint {
+    type Err = anyhow::Error;
+
+    fn from_str(url: &str) -> Result<Self, Self::Err> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| anyhow!("Invalid webhook url {url:?}: only http urls are supported"))?;
+        let (authority, path) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => (rest, "/"),
+        };
+
+        // IPv6 addresses are enclosed in brackets
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => (
+                host,
+                port.parse()
+                    .with_context(|| format!("Invalid port in webhook url {url:?}"))?,
+            ),
+            _ => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(anyhow!("Missing host in webhook url {url:?}"));
+        }
+
+        Ok(Self {
+            authority: authority.to_string(),
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+}
+
+// Methods for the Webhook struct
+impl Webhook {
+    /// Matches method checks if a message passes the filters of the webhook, direct messages are never sent
+    ///
+    /// Args:
+    ///     - message: new message
+    pub fn matches(&self, message: &Message) -> bool {
+        let config = &self.config;
+        let text = message.message.to_lowercase();
+
+        message.to.is_none()
+            && (config.rooms.is_empty() || config.rooms.contains(&message.room))
+            && (config.users.is_empty() || config.users.contains(&message.user))
+            && (config.keywords.is_empty()
+                || config
+                    .keywords
+                    .iter()
+                    .any(|keyword| text.contains(&keyword.to_lowercase())))
+    }
+}
+
+// Methods for the Webhooks struct
+impl Webhooks {
+    /// Start method checks the urls of the webhooks and spawns their delivery tasks, it must be called inside the tokio runtime
+    ///
+    /// Args:
+    ///     - configs: settings of the webhooks
+    pub fn start(configs: &[WebhookConfig]) -> Result<Self, anyhow::Error> {
+        let webhooks = configs
+            .iter()
+            .map(|config| {
+                let endpoint: Endpoint = config.url.parse()?;
+                let (queue, rx) = mpsc::channel(QUEUE_SIZE);
+                tokio::spawn(delivery_task(config.clone(), endpoint, rx));
+
+                Ok(Webhook {
+                    config: config.clone(),
+                    queue,
+                })
+            })
+            .collect::<Result<_, anyhow::Error>>()?;
+
+        Ok(Self { webhooks })
+    }
+
+    /// Is empty method checks if no webhook is configured
+    pub fn is_empty(&self) -> bool {
+        self.webhooks.is_empty()
+    }
+}
+
+// MessageHook implementation for Webhooks struct: the stored messages are queued for the webhooks they match
+impl MessageHook for Webhooks {
+    fn name(&self) -> &str {
+        "webhooks"
+    }
+
+    fn on_store(&self, message: &Message) {
+        for webhook in self
+            .webhooks
+            .iter()
+            .filter(|webhook| webhook.matches(message))
+        {
+            if webhook.queue.try_send(message.clone()).is_err() {
+                warn!(url = %webhook.config.url, id = message.id, "webhook queue full, message dropped");
+            }
+        }
+    }
+}
+
+/// Sign function computes the signature of a body with a secret (HMAC-SHA256, hex encoded),
+/// the receivers compare it with the X-Chat-Signature header
+///
+/// Args:
+///     - secret: secret of the webhook
+///     - body: body of the request
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+// Delivery task function posts the queued messages to a webhook in order, retrying the failed deliveries with backoff
+async fn delivery_task(config: WebhookConfig, endpoint: Endpoint, mut rx: mpsc::Receiver<Message>) {
+    while let Some(message) = rx.recv().await {
+        let body = match serde_json::to_string(&message) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!(?e, "failed to serialize the message");
+                continue;
+            }
+        };
+
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(60));
+        loop {
+            // Only the errors of the network and of the receiver (5xx) and the rate limits are worth a retry
+            let error = match post(&config, &endpoint, &body, message.id).await {
+                Ok(status) if (200..300).contains(&status) => {
+                    debug!(url = %config.url, id = message.id, "message delivered");
+                    break;
+                }
+                Ok(status) if status != 429 && status < 500 => {
+                    warn!(url = %config.url, id = message.id, status, "webhook refused the message");
+                    break;
+                }
+                Ok(status) => anyhow!("status {status}"),
+                Err(e) => e,
+            };
+
+            if backoff.attempt() >= config.max_retries {
+                warn!(url = %config.url, id = message.id, "giving up after {} attempts: {error:#}", backoff.attempt() + 1);
+                break;
+            }
+            let delay = backoff.next_delay();
+            warn!(url = %config.url, id = message.id, "delivery failed, retrying in {delay:?}: {error:#}");
+            sleep(delay).await;
+        }
+    }
+}
+
+// Post function sends a message to a webhook and returns the status code of the response
+async fn post(
+    config: &WebhookConfig,
+    endpoint: &Endpoint,
+    body: &str,
+    id: u64,
+) -> Result<u16, anyhow::Error> {
+    let signature = config
+        .secret
+        .as_deref()
+        .map(|secret| format!("{SIGNATURE_HEADER}: sha256={}\r\n", sign(secret, body)))
+        .unwrap_or_default();
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{MESSAGE_ID_HEADER}: {id}\r\n{signature}Connection: close\r\n\r\n{body}",
+        endpoint.path,
+        endpoint.authority,
+        body.len()
+    );
+
+    let attempt = async {
+        let socket_addr = resolve(&endpoint.host, endpoint.port).await?;
+        let mut stream = TcpStream::connect(socket_addr).await?;
+        stream.write_all(request.as_bytes()).await?;
+
+        // Only the status line of the response is needed
+        let mut status_line = String::new();
+        BufReader::new(&mut stream)
+            .read_line(&mut status_line)
+            .await?;
+        status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| anyhow!("Invalid response: {:?}", status_line.trim_end()))
+    };
+
+    timeout(Duration::from_secs(config.timeout_secs), attempt)
+        .await
+        .context("Timed out")?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{io::AsyncReadExt, net::TcpListener};
+
+    // Read request function reads a whole request received by the stub, returns its head and its body
+    async fn read_request(stream: &mut TcpStream) -> (String, String) {
+        let mut reader = BufReader::new(stream);
+        let mut head = String::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).await.unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            head.push_str(&line);
+        }
+
+        let len = head
+            .lines()
+            .find_map(|line| line.strip_prefix("Content-Length: "))
+            .map_or(0, |len| len.parse().unwrap());
+        let mut body = vec![0; len];
+        reader.read_exact(&mut body).await.unwrap();
+
+        (head, String::from_utf8(body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn delivers_signed_message_and_retries_after_server_error() {
+        // Stub receiver answering 500 to the first delivery and 200 to the second
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let stub = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for status in ["500 Internal Server Error", "200 OK"] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                requests.push(read_request(&mut stream).await);
+                let response = format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\n\r\n");
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+            requests
+        });
+
+        let config = WebhookConfig {
+            url: format!("http://127.0.0.1:{port}/hook"),
+            secret: Some("s3cret".to_string()),
+            ..Default::default()
+        };
+        let webhooks = Webhooks::start(&[config]).unwrap();
+        let mut message = Message::new("alice", "hello", "general");
+        message.id = 7;
+        webhooks.on_store(&message);
+
+        let requests = timeout(Duration::from_secs(10), stub)
+            .await
+            .unwrap()
+            .unwrap();
+        let expected_body = serde_json::to_string(&message).unwrap();
+        for (head, body) in &requests {
+            assert!(head.starts_with("POST /hook HTTP/1.1\r\n"));
+            assert!(head.contains(&format!("{MESSAGE_ID_HEADER}: 7\r\n")));
+            assert!(head.contains(&format!(
+                "{SIGNATURE_HEADER}: sha256={}\r\n",
+                sign("s3cret", body)
+            )));
+            assert_eq!(body, &expected_body);
+        }
+        assert_eq!(requests.len(), 2);
+    }
+
+    #[test]
+    fn signature_is_hmac_sha256() {
+        // Test vector 2 of RFC 4231
+        assert_eq!(
+            sign("Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
@@ -0,0 +1,58 @@
+/// Client args module
+// Necessary imports
+use chat_lib::{args::Args, config::TimestampFormat};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// Client args struct representing the cli arguments of the tui client, they override the config file
+#[derive(StructOpt, Debug)]
+#[structopt(name = "Chat-TUI", about = "Terminal client of the chat")]
+pub struct ClientArgs {
+    /// Arguments shared with the server (ip, port and config file)
+    #[structopt(flatten)]
+    pub args: Args,
+
+    /// Host: Hostname of the server, overrides --ip
+    #[structopt(short = "H", long, about = "Hostname of the server, overrides --ip")]
+    pub host: Option<String>,
+
+    /// Username: Log in with this username instead of asking for it
+    #[structopt(
+        short,
+        long,
+        about = "Log in with this username instead of asking for it"
+    )]
+    pub username: Option<String>,
+
+    /// Timestamps: How to display message timestamps (relative, absolute or none)
+    #[structopt(
+        short,
+        long,
+        about = "How to display message timestamps: relative, absolute or none (default: relative)"
+    )]
+    pub timestamps: Option<TimestampFormat>,
+
+    /// History file: File where the sent lines are saved to be recalled in the next sessions
+    #[structopt(
+        long,
+        parse(from_os_str),
+        about = "File where the sent lines are saved to be recalled in the next sessions"
+    )]
+    pub history_file: Option<PathBuf>,
+
+    /// Log file: File the logs are appended to, the client doesn't log without it
+    #[structopt(
+        long,
+        parse(from_os_str),
+        about = "File the logs are appended to (the client doesn't log without it)"
+    )]
+    pub log_file: Option<PathBuf>,
+
+    /// Bell: Ring the terminal bell when a new message mentions you
+    #[structopt(
+        short,
+        long,
+        about = "Ring the terminal bell when a new message mentions you"
+    )]
+    pub bell: bool,
+}
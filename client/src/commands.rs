@@ -0,0 +1,591 @@
+/// Commands module: slash commands typed in the tui input
+// Necessary imports
+use anyhow::anyhow;
+
+/// Command enum enumerates the slash commands available in the tui
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Change the username
+    Nick(String),
+    /// Switch to another room
+    Join(String),
+    /// Send a direct message to a user
+    Msg { to: String, text: String },
+    /// Send an action message
+    Me(String),
+    /// Clear the messages on screen
+    Clear,
+    /// Search through the chat history
+    Search(String),
+    /// Save a transcript of the current room to a file, optionally only of the last hours
+    Export { file: String, hours: Option<u64> },
+    /// Show the available commands or the usage of one of them
+    Help(Option<String>),
+    /// Quit the client
+    Quit,
+    /// Delete a message (the selected one if no id is given), admin only
+    Delete(Option<u64>),
+    /// Mute a user or an address, optionally for some minutes, admin only
+    Mute {
+        target: String,
+        minutes: Option<u64>,
+    },
+    /// Unmute a user or an address, admin only
+    Unmute(String),
+    /// Ban a user or an address, optionally for some minutes, admin only
+    Ban {
+        target: String,
+        minutes: Option<u64>,
+    },
+    /// Unban a user or an address, admin only
+    Unban(String),
+    /// Delete all the messages of a room (the current one if no room is given), admin only
+    ClearRoom(Option<String>),
+}
+
+/// ArgKind enum enumerates what the first argument of a command refers to, used for tab-completion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind {
+    /// The command takes no completable argument
+    None,
+    /// Username
+    User,
+    /// Room name
+    Room,
+    /// Command name
+    Command,
+}
+
+/// CommandSpec struct describing a command of the registry
+pub struct CommandSpec {
+    /// Name of the command, without the leading slash
+    pub name: &'static str,
+
+    /// Arguments of the command as shown in the help
+    pub usage: &'static str,
+
+    /// Short description of the command
+    pub help: &'static str,
+
+    /// Whether the command is only available to the admins
+    pub admin: bool,
+
+    /// Kind of the first argument of the command
+    pub arg: ArgKind,
+
+    /// Parser of the command arguments
+    parse: fn(&str) -> Result<Command, anyhow::Error>,
+}
+
+/// Registry of the slash commands
+pub static COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "nick",
+        usage: "<name>",
+        help: "Change your username",
+        admin: false,
+        arg: ArgKind::None,
+        parse: |args| single_arg(args, "name").map(Command::Nick),
+    },
+    CommandSpec {
+        name: "join",
+        usage: "<room>",
+        help: "Switch to another room",
+        admin: false,
+        arg: ArgKind::Room,
+        parse: |args| {
+            single_arg(args, "room").map(|room| Command::Join(room.trim_start_matches('#').into()))
+        },
+    },
+    CommandSpec {
+        name: "msg",
+        usage: "<user> <text>",
+        help: "Send a direct message (not confidential)",
+        admin: false,
+        arg: ArgKind::User,
+        parse: |args| {
+            let (to, text) = args
+                .split_once(char::is_whitespace)
+                .map(|(to, text)| (to, text.trim()))
+                .filter(|(_, text)| !text.is_empty())
+                .ok_or(anyhow!("usage: /msg <user> <text>"))?;
+            Ok(Command::Msg {
+                to: to.to_string(),
+                text: text.to_string(),
+            })
+        },
+    },
+    CommandSpec {
+        name: "me",
+        usage: "<action>",
+        help: "Describe an action",
+        admin: false,
+        arg: ArgKind::None,
+        parse: |args| match args {
+            "" => Err(anyhow!("usage: /me <action>")),
+            action => Ok(Command::Me(action.to_string())),
+        },
+    },
+    CommandSpec {
+        name: "clear",
+        usage: "",
+        help: "Clear the messages on screen",
+        admin: false,
+        arg: ArgKind::None,
+        parse: |_| Ok(Command::Clear),
+    },
+    CommandSpec {
+        name: "search",
+        usage: "<text>",
+        help: "Search the chat history (text between slashes is a regex)",
+        admin: false,
+        arg: ArgKind::None,
+        parse: |args| match args {
+            "" => Err(anyhow!("usage: /search <text>")),
+            text => Ok(Command::Search(text.to_string())),
+        },
+    },
+    CommandSpec {
+        name: "export",
+        usage: "<file> [hours]",
+        help: "Save the history of the room to a .jsonl, .txt, .md or .html file",
+        admin: false,
+        arg: ArgKind::None,
+        parse: |args| match args.split_whitespace().collect::<Vec<_>>()[..] {
+            [file] => Ok(Command::Export {
+                file: file.to_string(),
+                hours: None,
+            }),
+            [file, hours] => hours
+                .parse()
+                .map(|hours| Command::Export {
+                    file: file.to_string(),
+                    hours: Some(hours),
+                })
+                .map_err(|_| anyhow!("invalid number of hours: {hours}")),
+            _ => Err(anyhow!("usage: /export <file> [hours]")),
+        },
+    },
+    CommandSpec {
+        name: "help",
+        usage: "[command]",
+        help: "Show the available commands",
+        admin: false,
+        arg: ArgKind::Command,
+        parse: |args| match args {
+            "" => Ok(Command::Help(None)),
+            name => Ok(Command::Help(Some(
+                name.trim_start_matches('/').to_string(),
+            ))),
+        },
+    },
+    CommandSpec {
+        name: "quit",
+        usage: "",
+        help: "Quit the client",
+        admin: false,
+        arg: ArgKind::None,
+        parse: |_| Ok(Command::Quit),
+    },
+    CommandSpec {
+        name: "delete",
+        usage: "[id]",
+        help: "Delete a message, the selected one if no id is given (admin)",
+        admin: true,
+        arg: ArgKind::None,
+        parse: |args| match args {
+            "" => Ok(Command::Delete(None)),
+            id => id
+                .trim_start_matches('#')
+                .parse()
+                .map(|id| Command::Delete(Some(id)))
+                .map_err(|_| anyhow!("invalid message id: {id}")),
+        },
+    },
+    CommandSpec {
+        name: "mute",
+        usage: "<user|ip> [minutes]",
+        help: "Prevent a user or an address from sending messages (admin)",
+        admin: true,
+        arg: ArgKind::User,
+        parse: |args| {
+            target_and_minutes(args, "/mute")
+                .map(|(target, minutes)| Command::Mute { target, minutes })
+        },
+    },
+    CommandSpec {
+        name: "unmute",
+        usage: "<user|ip>",
+        help: "Allow a muted user or address to send messages again (admin)",
+        admin: true,
+        arg: ArgKind::User,
+        parse: |args| single_arg(args, "user|ip").map(Command::Unmute),
+    },
+    CommandSpec {
+        name: "ban",
+        usage: "<user|ip> [minutes]",
+        help: "Prevent a user or an address from using the chat (admin)",
+        admin: true,
+        arg: ArgKind::User,
+        parse: |args| {
+            target_and_minutes(args, "/ban")
+                .map(|(target, minutes)| Command::Ban { target, minutes })
+        },
+    },
+    CommandSpec {
+        name: "unban",
+        usage: "<user|ip>",
+        help: "Allow a banned user or address to use the chat again (admin)",
+        admin: true,
+        arg: ArgKind::User,
+        parse: |args| single_arg(args, "user|ip").map(Command::Unban),
+    },
+    CommandSpec {
+        name: "clearroom",
+        usage: "[room]",
+        help: "Delete all the messages of a room, the current one if not given (admin)",
+        admin: true,
+        arg: ArgKind::Room,
+        parse: |args| match args {
+            "" => Ok(Command::ClearRoom(None)),
+            room => single_arg(room, "room")
+                .map(|room| Command::ClearRoom(Some(room.trim_start_matches('#').into()))),
+        },
+    },
+];
+
+// Single arg function checks that exactly one argument was passed to a command
+fn single_arg(args: &str, name: &str) -> Result<String, anyhow::Error> {
+    match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [arg] => Ok(arg.to_string()),
+        _ => Err(anyhow!("expected a single <{name}> argument")),
+    }
+}
+
+// Target and minutes function parses the arguments of /mute and /ban: a user or address and an optional duration
+fn target_and_minutes(args: &str, usage: &str) -> Result<(String, Option<u64>), anyhow::Error> {
+    match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [target] => Ok((target.to_string(), None)),
+        [target, minutes] => minutes
+            .parse()
+            .map(|minutes| (target.to_string(), Some(minutes)))
+            .map_err(|_| anyhow!("invalid number of minutes: {minutes}")),
+        _ => Err(anyhow!("usage: {usage} <user|ip> [minutes]")),
+    }
+}
+
+/// Available function returns the commands of the registry the user can run
+///
+/// Args:
+///     - admin: whether the user is an admin
+pub fn available(admin: bool) -> impl Iterator<Item = &'static CommandSpec> {
+    COMMANDS
+        .iter()
+        .filter(move |command| admin || !command.admin)
+}
+
+/// Find function looks up a command the user can run in the registry
+///
+/// Args:
+///     - name: name of the command, without the leading slash
+///     - admin: whether the user is an admin
+pub fn find(name: &str, admin: bool) -> Option<&'static CommandSpec> {
+    available(admin).find(|command| command.name == name)
+}
+
+/// Parse function parses the input as a slash command
+///
+/// Returns None if the input is not a command (a leading "//" escapes the slash)
+///
+/// Args:
+///     - input: text of the input
+///     - admin: whether the user is an admin
+pub fn parse(input: &str, admin: bool) -> Result<Option<Command>, anyhow::Error> {
+    let Some(line) = input.strip_prefix('/') else {
+        return Ok(None);
+    };
+    if line.starts_with('/') {
+        return Ok(None);
+    }
+
+    let (name, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let command = find(name, admin).ok_or(anyhow!("unknown command /{name}, try /help"))?;
+
+    (command.parse)(args.trim()).map(Some)
+}
+
+/// Message text function returns the text the input line sends as a message, None if the line is not a message
+///
+/// Args:
+///     - input: text of the input
+pub fn message_text(input: &str) -> Option<&str> {
+    let Some(line) = input.strip_prefix('/') else {
+        return Some(input);
+    };
+    if line.starts_with('/') {
+        return Some(line);
+    }
+
+    // Only /me and /msg send a message, the text follows the command name (and the recipient)
+    let (name, args) = line.split_once(char::is_whitespace)?;
+    match name {
+        "me" => Some(args),
+        "msg" => args
+            .trim_start()
+            .split_once(char::is_whitespace)
+            .map(|(_, text)| text),
+        _ => None,
+    }
+}
+
+/// Completion struct representing the result of a tab-completion
+#[derive(Debug)]
+pub struct Completion {
+    /// Input after the completion
+    pub input: String,
+
+    /// All the candidates matching the completed word
+    pub candidates: Vec<String>,
+}
+
+/// Complete function completes the command name or the first argument of the command in the input,
+/// in plain messages it completes the usernames (with or without a leading @)
+///
+/// Args:
+///     - input: text of the input
+///     - users: known usernames
+///     - rooms: known rooms
+///     - admin: whether the user is an admin
+pub fn complete(
+    input: &str,
+    users: &[String],
+    rooms: &[String],
+    admin: bool,
+) -> Option<Completion> {
+    let (head, word) = split_last_word(input);
+
+    let candidates: Vec<String> = if !input.starts_with('/') {
+        // Completion of a username in a message
+        let at = if word.starts_with('@') { "@" } else { "" };
+        users.iter().map(|user| format!("{at}{user}")).collect()
+    } else if head.is_empty() {
+        // Completion of the command name
+        available(admin)
+            .map(|command| format!("/{}", command.name))
+            .collect()
+    } else {
+        // Only the first argument of a command is completed
+        let name = head.strip_prefix('/')?.trim_end();
+        if name.contains(char::is_whitespace) {
+            return None;
+        }
+
+        match find(name, admin)?.arg {
+            ArgKind::None => return None,
+            ArgKind::User => users.to_vec(),
+            ArgKind::Room => rooms.to_vec(),
+            ArgKind::Command => available(admin).map(|c| c.name.to_string()).collect(),
+        }
+    };
+
+    complete_word(head, word, candidates)
+}
+
+/// Split last word function splits the input into everything before the last word and the last word
+///
+/// Args:
+///     - input: text of the input
+pub fn split_last_word(input: &str) -> (&str, &str) {
+    let start = input
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_whitespace())
+        .map_or(0, |(i, c)| i + c.len_utf8());
+
+    input.split_at(start)
+}
+
+/// Complete word function replaces the word with the candidates starting with it:
+/// a single match is completed entirely, multiple matches up to their common prefix
+///
+/// Args:
+///     - head: input before the word
+///     - word: word to complete
+///     - candidates: possible completions
+pub fn complete_word(head: &str, word: &str, candidates: Vec<String>) -> Option<Completion> {
+    let mut candidates: Vec<String> = candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(word))
+        .collect();
+    candidates.sort();
+    candidates.dedup();
+
+    let input = match &candidates[..] {
+        [] => return None,
+        [candidate] => format!("{head}{candidate} "),
+        [first, rest @ ..] => {
+            let prefix = rest.iter().fold(first.as_str(), |prefix, candidate| {
+                let len = prefix
+                    .char_indices()
+                    .zip(candidate.chars())
+                    .take_while(|((_, a), b)| a == b)
+                    .last()
+                    .map_or(0, |((i, a), _)| i + a.len_utf8());
+                &prefix[..len]
+            });
+            format!("{head}{prefix}")
+        }
+    };
+
+    Some(Completion { input, candidates })
+}
+
+/// Help function returns the help text of a command or the list of all the commands
+///
+/// Args:
+///     - name: name of the command, None for the list of all the commands
+///     - admin: whether the user is an admin
+pub fn help(name: Option<&str>, admin: bool) -> Result<String, anyhow::Error> {
+    match name {
+        Some(name) => {
+            let command = find(name, admin).ok_or(anyhow!("unknown command /{name}"))?;
+            Ok(format!(
+                "/{} {} - {}",
+                command.name, command.usage, command.help
+            ))
+        }
+        None => Ok(format!(
+            "Commands: {}",
+            available(admin)
+                .map(|command| format!("/{}", command.name))
+                .collect::<Vec<_>>()
+                .join(" ")
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Names function turns string literals into the owned names passed to the completion
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_reads_the_arguments_of_the_commands() {
+        assert_eq!(parse("hello", false).unwrap(), None);
+        assert_eq!(parse("//nick", false).unwrap(), None);
+        assert_eq!(
+            parse("/join #games", false).unwrap(),
+            Some(Command::Join("games".to_string()))
+        );
+        assert_eq!(
+            parse("/msg bob  see you later ", false).unwrap(),
+            Some(Command::Msg {
+                to: "bob".to_string(),
+                text: "see you later".to_string()
+            })
+        );
+        assert_eq!(
+            parse("/export log.md 12", false).unwrap(),
+            Some(Command::Export {
+                file: "log.md".to_string(),
+                hours: Some(12)
+            })
+        );
+        assert_eq!(parse("/quit", false).unwrap(), Some(Command::Quit));
+    }
+
+    #[test]
+    fn parse_rejects_invalid_arguments() {
+        assert_eq!(
+            parse("/msg bob", false).unwrap_err().to_string(),
+            "usage: /msg <user> <text>"
+        );
+        assert_eq!(
+            parse("/nick a b", false).unwrap_err().to_string(),
+            "expected a single <name> argument"
+        );
+        assert_eq!(
+            parse("/export log.md soon", false).unwrap_err().to_string(),
+            "invalid number of hours: soon"
+        );
+    }
+
+    #[test]
+    fn parse_rejects_unknown_and_admin_commands() {
+        assert_eq!(
+            parse("/dance", false).unwrap_err().to_string(),
+            "unknown command /dance, try /help"
+        );
+        assert!(parse("/ban bob", false).is_err());
+        assert_eq!(
+            parse("/ban bob 10", true).unwrap(),
+            Some(Command::Ban {
+                target: "bob".to_string(),
+                minutes: Some(10)
+            })
+        );
+    }
+
+    #[test]
+    fn message_text_returns_the_text_sent() {
+        assert_eq!(message_text("hi"), Some("hi"));
+        assert_eq!(message_text("//not a command"), Some("/not a command"));
+        assert_eq!(message_text("/me waves"), Some("waves"));
+        assert_eq!(message_text("/msg bob hi there"), Some("hi there"));
+        assert_eq!(message_text("/nick bob"), None);
+    }
+
+    #[test]
+    fn complete_command_names_by_prefix() {
+        let completion = complete("/qu", &[], &[], false).unwrap();
+        assert_eq!(completion.input, "/quit ");
+
+        // Several matches are completed up to their common prefix
+        let completion = complete("/c", &[], &[], true).unwrap();
+        assert_eq!(completion.input, "/clear");
+        assert_eq!(completion.candidates, names(&["/clear", "/clearroom"]));
+
+        // The admin commands are only completed for the admins
+        assert!(complete("/ba", &[], &[], false).is_none());
+    }
+
+    #[test]
+    fn complete_the_first_argument_by_kind() {
+        let users = names(&["alice", "albert", "bob"]);
+        let rooms = names(&["general", "games"]);
+
+        assert_eq!(
+            complete("/msg b", &users, &rooms, false).unwrap().input,
+            "/msg bob "
+        );
+        assert_eq!(
+            complete("/join ga", &users, &rooms, false).unwrap().input,
+            "/join games "
+        );
+        assert_eq!(
+            complete("/help ex", &users, &rooms, false).unwrap().input,
+            "/help export "
+        );
+        assert!(complete("/nick b", &users, &rooms, false).is_none());
+        assert!(complete("/msg bob hi a", &users, &rooms, false).is_none());
+    }
+
+    #[test]
+    fn complete_usernames_in_messages() {
+        let users = names(&["alice", "albert"]);
+
+        assert_eq!(
+            complete("hi @alb", &users, &[], false).unwrap().input,
+            "hi @albert "
+        );
+        assert_eq!(
+            complete("hi al", &users, &[], false).unwrap().input,
+            "hi al"
+        );
+        assert!(complete("hi z", &users, &[], false).is_none());
+    }
+}
@@ -0,0 +1,221 @@
+/// History module: history of the lines sent from the tui input
+// Necessary imports
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Maximum number of lines kept in the history
+const MAX_ENTRIES: usize = 1000;
+
+/// Number of lines after which the history file is trimmed to the last MAX_ENTRIES lines
+const MAX_FILE_LINES: usize = 2 * MAX_ENTRIES;
+
+/// History struct containing the sent lines and the navigation state
+#[derive(Debug, Default)]
+pub struct History {
+    /// Sent lines, from the oldest to the newest
+    entries: Vec<String>,
+
+    /// Index of the entry shown in the input while navigating, None when editing a new line
+    position: Option<usize>,
+
+    /// Line that was being edited before the navigation started
+    draft: String,
+
+    /// File the history is persisted to, if any
+    file: Option<PathBuf>,
+
+    /// Number of lines in the file
+    file_lines: usize,
+}
+
+// Methods for the History struct
+impl History {
+    /// Constructor method, loads the persisted lines if a file is given
+    ///
+    /// Args:
+    ///     - file: file the history is persisted to
+    pub fn new(file: Option<PathBuf>) -> Self {
+        let mut entries: Vec<String> = file
+            .as_ref()
+            .and_then(|file| fs::read_to_string(file).ok())
+            .map(|content| content.lines().map(Into::into).collect())
+            .unwrap_or_default();
+        let len = entries.len();
+        entries.drain(..len.saturating_sub(MAX_ENTRIES));
+
+        Self {
+            entries,
+            file,
+            file_lines: len,
+            ..Default::default()
+        }
+    }
+
+    /// Push method adds a sent line to the history and resets the navigation
+    ///
+    /// Args:
+    ///     - line: sent line
+    pub fn push(&mut self, line: &str) {
+        self.position = None;
+        self.draft.clear();
+
+        // Empty lines and repetitions of the last line are not recorded
+        if line.trim().is_empty() || self.entries.last().is_some_and(|last| last == line) {
+            return;
+        }
+
+        self.entries.push(line.to_string());
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+
+        // Persisting the history is best effort, a failure must not stop the chat
+        if let Some(file) = &self.file {
+            if self.file_lines >= MAX_FILE_LINES {
+                self.file_lines =
+                    Self::rewrite(file, &self.entries).map_or(0, |()| self.entries.len());
+            } else if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(file)
+                && writeln!(file, "{line}").is_ok()
+            {
+                self.file_lines += 1;
+            }
+        }
+    }
+
+    // Rewrite function replaces the history file with the given lines, through a temporary file
+    // so that a failure doesn't lose the history
+    fn rewrite(file: &Path, entries: &[String]) -> io::Result<()> {
+        let tmp = PathBuf::from(format!("{}.tmp", file.display()));
+        let mut content = entries.join("\n");
+        content.push('\n');
+        fs::write(&tmp, content)?;
+        fs::rename(&tmp, file)
+    }
+
+    /// Previous method moves to the previous (older) line of the history
+    ///
+    /// Args:
+    ///     - current: line in the input, saved as draft when the navigation starts
+    pub fn previous(&mut self, current: &str) -> Option<&str> {
+        let position = match self.position {
+            None if self.entries.is_empty() => return None,
+            None => {
+                self.draft = current.to_string();
+                self.entries.len() - 1
+            }
+            Some(position) => position.saturating_sub(1),
+        };
+
+        self.position = Some(position);
+        Some(&self.entries[position])
+    }
+
+    /// Next method moves to the next (newer) line of the history, past the newest line the draft is returned
+    pub fn next(&mut self) -> Option<&str> {
+        let position = self.position?;
+
+        if position + 1 < self.entries.len() {
+            self.position = Some(position + 1);
+            Some(&self.entries[position + 1])
+        } else {
+            self.position = None;
+            Some(&self.draft)
+        }
+    }
+
+    /// Search method looks for the newest line containing the query, older than the given index
+    ///
+    /// Args:
+    ///     - query: text to look for
+    ///     - before: only lines with an index lower than this are searched
+    pub fn search(&self, query: &str, before: usize) -> Option<(usize, &str)> {
+        self.entries[..before.min(self.entries.len())]
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, line)| line.contains(query))
+            .map(|(i, line)| (i, line.as_str()))
+    }
+
+    /// Len method returns the number of lines in the history
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // History function builds a history without file from some lines
+    fn history(lines: &[&str]) -> History {
+        let mut history = History::new(None);
+        for line in lines {
+            history.push(line);
+        }
+        history
+    }
+
+    #[test]
+    fn push_skips_empty_lines_and_repetitions() {
+        let history = history(&["a", "", "a", "b", "  ", "a"]);
+
+        assert_eq!(history.entries, vec!["a", "b", "a"]);
+    }
+
+    #[test]
+    fn navigation_returns_to_the_draft() {
+        let mut history = history(&["first", "second"]);
+
+        assert_eq!(history.previous("draft"), Some("second"));
+        assert_eq!(history.previous("second"), Some("first"));
+        assert_eq!(history.previous("first"), Some("first"));
+        assert_eq!(history.next(), Some("second"));
+        assert_eq!(history.next(), Some("draft"));
+        assert_eq!(history.next(), None);
+    }
+
+    #[test]
+    fn search_finds_the_newest_match_before_the_index() {
+        let history = history(&["cargo build", "ls", "cargo test", "cd"]);
+
+        assert_eq!(
+            history.search("cargo", history.len()),
+            Some((2, "cargo test"))
+        );
+        assert_eq!(history.search("cargo", 2), Some((0, "cargo build")));
+        assert_eq!(history.search("cargo", 0), None);
+        assert_eq!(history.search("git", history.len()), None);
+    }
+
+    #[test]
+    fn file_is_trimmed_to_the_last_entries() {
+        let file = std::env::temp_dir().join(format!("chat-history-{}", std::process::id()));
+        let _ = fs::remove_file(&file);
+
+        let mut history = History::new(Some(file.clone()));
+        for i in 0..MAX_FILE_LINES + 1 {
+            history.push(&format!("line {i}"));
+        }
+
+        // The file reached its limit and was rewritten with the entries in memory
+        let content = fs::read_to_string(&file).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), MAX_ENTRIES);
+        assert_eq!(
+            lines.last(),
+            Some(&format!("line {MAX_FILE_LINES}").as_str())
+        );
+
+        // The next lines are appended again, and a new history loads the last entries
+        history.push("after");
+        let reloaded = History::new(Some(file.clone()));
+        assert_eq!(reloaded.len(), MAX_ENTRIES);
+        assert_eq!(reloaded.entries.last().map(String::as_str), Some("after"));
+
+        fs::remove_file(&file).unwrap();
+    }
+}
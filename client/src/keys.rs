@@ -0,0 +1,167 @@
+/// Keys module: key bindings of the tui
+// Necessary imports
+use anyhow::anyhow;
+use chat_lib::config::KeyBindings;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::str::FromStr;
+
+/// KeyBinding struct representing a key combination, e.g. ctrl-f
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    /// Pressed key
+    code: KeyCode,
+
+    /// Modifiers held down with the key
+    modifiers: KeyModifiers,
+}
+
+/// Keys struct containing the key bindings of the tui actions parsed from the configuration
+#[derive(Debug, Clone)]
+pub struct Keys {
+    /// Quit the client
+    pub quit: KeyBinding,
+
+    /// Reverse search in the input history
+    pub history_search: KeyBinding,
+
+    /// Search through the chat history
+    pub message_search: KeyBinding,
+
+    /// Complete the command, argument or username being typed
+    pub complete: KeyBinding,
+
+    /// Send again the messages that were not delivered
+    pub retry: KeyBinding,
+
+    /// Drop the newest message that was not delivered
+    pub discard: KeyBinding,
+}
+
+// Methods for the KeyBinding struct
+impl KeyBinding {
+    /// Matches method checks if a key event is this key combination, for characters shift is ignored
+    /// since it is already in their case (the terminals don't all report it)
+    ///
+    /// Args:
+    ///     - key: key event
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        let ignored = match self.code {
+            KeyCode::Char(_) => KeyModifiers::SHIFT,
+            _ => KeyModifiers::NONE,
+        };
+        key.code == self.code
+            && key.modifiers.difference(ignored) == self.modifiers.difference(ignored)
+    }
+}
+
+// FromStr implementation for KeyBinding struct: parses key combinations like "ctrl-f", "alt-x", "shift-x", "tab" or "f2",
+// the modifiers and the names of the keys are case insensitive while the characters keep their case
+impl FromStr for KeyBinding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('-').collect();
+        let key = parts.pop().filter(|key| !key.is_empty());
+
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in parts {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                m => return Err(anyhow!("invalid key modifier in {s}: {m}")),
+            };
+        }
+
+        let key = key.ok_or(anyhow!("missing key in key binding: {s}"))?;
+        let code = match key.to_lowercase().as_str() {
+            "tab" => KeyCode::Tab,
+            "esc" => KeyCode::Esc,
+            "enter" => KeyCode::Enter,
+            "space" => KeyCode::Char(' '),
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            // With shift the terminal sends the uppercase character
+            _ if key.chars().count() == 1 => match key.chars().next().unwrap() {
+                c if modifiers.contains(KeyModifiers::SHIFT) => {
+                    KeyCode::Char(c.to_uppercase().next().unwrap_or(c))
+                }
+                c => KeyCode::Char(c),
+            },
+            lower => match lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                Some(n) => KeyCode::F(n),
+                None => return Err(anyhow!("invalid key in key binding: {key}")),
+            },
+        };
+
+        Ok(KeyBinding { code, modifiers })
+    }
+}
+
+// TryFrom<&KeyBindings> implementation for Keys struct: parses the key bindings of the configuration
+impl TryFrom<&KeyBindings> for Keys {
+    type Error = anyhow::Error;
+
+    fn try_from(bindings: &KeyBindings) -> Result<Self, Self::Error> {
+        Ok(Keys {
+            quit: bindings.quit.parse()?,
+            history_search: bindings.history_search.parse()?,
+            message_search: bindings.message_search.parse()?,
+            complete: bindings.complete.parse()?,
+            retry: bindings.retry.parse()?,
+            discard: bindings.discard.parse()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Event function builds the key event of a key combination
+    fn event(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn parse_modifiers_and_named_keys_ignoring_the_case() {
+        let binding: KeyBinding = "Ctrl-Alt-PageUp".parse().unwrap();
+        assert!(binding.matches(&event(
+            KeyCode::PageUp,
+            KeyModifiers::CONTROL | KeyModifiers::ALT
+        )));
+        assert!(!binding.matches(&event(KeyCode::PageUp, KeyModifiers::CONTROL)));
+
+        let binding: KeyBinding = "f2".parse().unwrap();
+        assert!(binding.matches(&event(KeyCode::F(2), KeyModifiers::NONE)));
+    }
+
+    #[test]
+    fn parse_keeps_the_case_of_the_characters() {
+        let binding: KeyBinding = "ctrl-f".parse().unwrap();
+        assert!(binding.matches(&event(KeyCode::Char('f'), KeyModifiers::CONTROL)));
+
+        // Shift is matched by the uppercase character, whether the terminal reports the modifier or not
+        for binding in ["shift-x", "X"] {
+            let binding: KeyBinding = binding.parse().unwrap();
+            assert!(binding.matches(&event(KeyCode::Char('X'), KeyModifiers::SHIFT)));
+            assert!(binding.matches(&event(KeyCode::Char('X'), KeyModifiers::NONE)));
+            assert!(!binding.matches(&event(KeyCode::Char('x'), KeyModifiers::NONE)));
+        }
+    }
+
+    #[test]
+    fn parse_rejects_invalid_bindings() {
+        assert_eq!(
+            "meta-x".parse::<KeyBinding>().unwrap_err().to_string(),
+            "invalid key modifier in meta-x: meta"
+        );
+        assert_eq!(
+            "ctrl-".parse::<KeyBinding>().unwrap_err().to_string(),
+            "missing key in key binding: ctrl-"
+        );
+        assert!("ctrl-home".parse::<KeyBinding>().is_err());
+    }
+}
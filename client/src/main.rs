@@ -1,99 +1,127 @@
 /// Main function for the tui client
 // Necesary imports
 use anyhow::anyhow;
-use chat_lib::{args::Args, messages::Message, response::parse_response, IP_ADDR, PORT};
+use args::ClientArgs;
+use chat_lib::{
+    client::{fetch_policy, resolve},
+    config::ClientConfig,
+    logging,
+    messages::DEFAULT_ROOM,
+    validation::ValidationPolicy,
+};
+use history::History;
 use std::io::stdin;
 use structopt::StructOpt;
 use tokio::{
     io::{stdout, AsyncWriteExt},
-    net::TcpStream,
-    sync::mpsc,
+    sync::{mpsc, watch},
 };
-use tui::App;
-
+use tracing::info;
+use tui::{App, Subscription};
+
+mod args;
+mod commands;
+mod history;
+mod keys;
+mod net;
+mod theme;
 mod tui;
 
-// Login function: used to get the username
-async fn login() -> Result<String, anyhow::Error> {
+// Login function: used to get the username, it asks again until the username follows the policy
+async fn login(policy: &ValidationPolicy) -> Result<String, anyhow::Error> {
     let mut stdout = stdout();
 
-    if cfg!(target_os = "windows") {
-        print!("{esc}[2J{esc}[1;1H", esc = 27 as char);
-    } else {
-        print!("{esc}[2J{esc}[1;1H", esc = 27 as char);
-    }
+    // Clear the screen
+    print!("{esc}[2J{esc}[1;1H", esc = 27 as char);
 
     println!("Inserisci un nome utente:");
 
-    stdout.write_all(b"\n\n> ").await?;
-    stdout.flush().await?;
+    loop {
+        stdout.write_all(b"\n\n> ").await?;
+        stdout.flush().await?;
 
-    let mut input = String::new();
-    stdin().read_line(&mut input)?;
-    let user = input.trim_end().to_string();
+        let mut input = String::new();
+        stdin().read_line(&mut input)?;
+        let user = input.trim_end().to_string();
 
-    Ok(user)
+        match policy.validate_name("Username", &user) {
+            Ok(()) => return Ok(user),
+            Err(e) => println!("{e}"),
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
-    // Cli args
-    let Args { ip, port } = Args::from_args();
-    let ip_addr = if ip.is_some() {
-        ip.unwrap()
-    } else {
-        IP_ADDR.to_string()
-    };
-
-    let port = if port.is_some() {
-        port.unwrap()
-    } else {
-        PORT.to_string()
-    };
-
-    let user = login().await?;
-    let (tx, mut rx) = mpsc::channel(100); // Tokio channel to send messages from one task to the other
-
-    let socket_addr = format!("{}:{}", ip_addr, port); // Socket address of the server
-
-    let socket_addr2 = socket_addr.clone(); // Second socket addr for the second task
-                                            // Receiver handle task, it connects to the server gets all the messages in realtime
-    let _ = tokio::spawn(async move {
-        loop {
-            // Connection to the server
-            let mut stream = TcpStream::connect(socket_addr.clone())
-                .await
-                .expect("Failed to connect");
-
-            // Send GET request at /messages endpoint
-            let request = "GET /messages HTTP/1.1\r\nHost: localhost\r\n\r\n";
-            let _ = stream.write_all(request.as_bytes()).await;
+    // Configuration from the config file, the environment variables and the cli args
+    let client_args = ClientArgs::from_args();
+    let mut config = ClientConfig::load(&client_args.args)?;
+    if let Some(host) = client_args.host {
+        config.host = host;
+    }
+    if let Some(username) = client_args.username {
+        config.username = Some(username);
+    }
+    if let Some(timestamps) = client_args.timestamps {
+        config.timestamps = timestamps;
+    }
+    if let Some(history_file) = client_args.history_file {
+        config.history_file = Some(history_file);
+    }
+    if let Some(log_file) = client_args.log_file {
+        config.log.file = Some(log_file);
+    }
+    config.bell |= client_args.bell;
 
-            // Read the response and get the messages
-            let response = parse_response(&mut stream)
-                .await
-                .expect("Failed to parse response");
+    // The logs are written only to a file, the terminal belongs to the tui
+    if config.log.file.is_some() {
+        logging::init(&config.log)?;
+    }
 
-            // Deserialize them from json to struct Vec<Message>
-            let messages: Vec<Message> =
-                serde_json::from_str::<Vec<Message>>(response.body.as_str())
-                    .expect("Failed to deserialize messages");
+    // Socket address of the server, resolved before starting so that a wrong host fails immediately
+    let socket_addr = resolve(&config.host, config.port).await?;
 
-            // Send them to the other task
-            tx.send(messages).await.expect("Failed to send messages");
+    // Rules of the messages, the default ones are used if the server is not reachable yet
+    let policy = fetch_policy(socket_addr).await.unwrap_or_default();
 
-            // Delay of 100 ms
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    // The login is skipped if the username is configured
+    let user = match config.username.clone() {
+        Some(user) => {
+            policy.validate_name("Username", &user)?;
+            user
         }
+        None => login(&policy).await?,
+    };
+    info!(%user, server = %socket_addr, "client started");
+    let app = App::new(
+        user.clone(),
+        &config,
+        History::new(config.history_file.clone()),
+        policy,
+    )?;
+
+    let (tx, mut rx) = mpsc::channel(100); // Tokio channel to send the updates from the network tasks to the tui
+    let (outgoing_tx, outgoing_rx) = mpsc::channel(100); // Tokio channel to send the messages written in the tui and the outbox requests
+
+    // Watch channel to tell the receiver task which user and room to fetch the messages for
+    let (sub_tx, sub_rx) = watch::channel(Subscription {
+        user: user.clone(),
+        room: DEFAULT_ROOM.to_string(),
     });
 
+    // Receiver handle task, it connects to the server gets all the messages in realtime
+    let _receiver_handle = tokio::spawn(net::receiver_task(socket_addr, sub_rx, tx.clone()));
+
+    // Sender handle task, it sends the messages written in the tui (queuing them while disconnected)
+    let _sender_handle = tokio::spawn(net::sender_task(socket_addr, outgoing_rx, tx));
+
     // Tui handle task
     let tui_handle = tokio::spawn(async move {
         // Initialize the terminal
         let mut terminal = ratatui::init();
 
-        let result = App::new(user)
-            .run(&mut terminal, &mut rx, &socket_addr2)
+        let result = app
+            .run(&mut terminal, &mut rx, &sub_tx, &outgoing_tx, socket_addr)
             .await;
 
         // Restore the terminal once the client is closed
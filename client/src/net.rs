@@ -0,0 +1,268 @@
+/// Net module: background tasks talking with the server
+// Necessary imports
+use crate::tui::Subscription;
+use chat_lib::{
+    client::{fetch_messages, send_message, Backoff, SendError},
+    messages::Message,
+};
+use std::{collections::VecDeque, net::SocketAddr, time::Duration};
+use tokio::{
+    select,
+    sync::{mpsc, watch},
+    time::{sleep, sleep_until, Instant},
+};
+use tracing::{debug, info, warn};
+
+/// Delay between two fetches of the messages
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Update enum enumerates what the background tasks report to the tui
+#[derive(Debug)]
+pub enum Update {
+    /// Messages of the current room
+    Messages(Vec<Message>),
+    /// State of the connection with the server
+    Connection(ConnectionStatus),
+    /// New delivery state of a message of the outbox
+    Delivery { id: u64, delivery: Delivery },
+    /// The server is refusing the messages because they are sent too fast, they are sent again after the delay
+    SlowDown(Duration),
+}
+
+/// Outgoing enum enumerates the requests of the tui to the sender task
+#[derive(Debug)]
+pub enum Outgoing {
+    /// Send a new message, identified in the outbox by a local id
+    Send { id: u64, message: Message },
+    /// Send again the failed messages and retry the pending ones without waiting
+    Retry,
+    /// Drop a message that was not delivered yet
+    Discard(u64),
+}
+
+/// Delivery enum enumerates the delivery states of a message of the outbox
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Delivery {
+    /// Waiting to be sent, it is retried when the server is reachable again
+    Pending,
+    /// Received by the server, that assigned it the id
+    Sent(u64),
+    /// Refused by the server, it is only sent again on request
+    Failed(String),
+}
+
+/// OutboxEntry struct containing a message sent from the tui and its delivery state
+#[derive(Debug, Clone)]
+pub struct OutboxEntry {
+    /// Local id of the message
+    pub id: u64,
+
+    /// Message sent
+    pub message: Message,
+
+    /// Delivery state of the message
+    pub delivery: Delivery,
+}
+
+/// ConnectionStatus enum enumerates the states of the connection with the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    /// The server is reachable
+    Connected,
+    /// The server is not reachable, the next attempt is after the delay
+    Reconnecting { attempt: u32, retry_in: Duration },
+}
+
+/// Receiver task function fetches the messages of the current room in loop,
+/// reconnecting with exponential backoff when the server is not reachable
+///
+/// Args:
+///     - socket_addr: socket address of the server
+///     - subscription: receiver of the current user and room
+///     - updates: sender of the updates to the tui
+pub async fn receiver_task(
+    socket_addr: SocketAddr,
+    subscription: watch::Receiver<Subscription>,
+    updates: mpsc::Sender<Update>,
+) {
+    let mut backoff = Backoff::default();
+
+    loop {
+        // Send GET request at /messages endpoint for the current room and get the messages
+        let Subscription { user, room } = subscription.borrow().clone();
+        let update = match fetch_messages(socket_addr, &room, &user).await {
+            Ok(messages) => {
+                if backoff.attempt() > 0 {
+                    backoff.reset();
+                    info!("reconnected to the server");
+                    report(&updates, Update::Connection(ConnectionStatus::Connected)).await;
+                }
+                Update::Messages(messages)
+            }
+            Err(e) => {
+                let retry_in = backoff.next_delay();
+                warn!(error = %e, attempt = backoff.attempt(), "failed to fetch the messages, retrying in {retry_in:?}");
+                let status = ConnectionStatus::Reconnecting {
+                    attempt: backoff.attempt(),
+                    retry_in,
+                };
+                report(&updates, Update::Connection(status)).await;
+                sleep(retry_in).await;
+                continue;
+            }
+        };
+
+        // The task ends when the tui is closed
+        if updates.send(update).await.is_err() {
+            return;
+        }
+
+        sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Sender task function sends the messages written in the tui, in order: while the server is
+/// not reachable they stay pending and are retried with exponential backoff, the messages refused
+/// by the server are kept as failed until the tui asks to retry or discard them
+///
+/// Args:
+///     - socket_addr: socket address of the server
+///     - outgoing: receiver of the requests of the tui
+///     - updates: sender of the updates to the tui
+pub async fn sender_task(
+    socket_addr: SocketAddr,
+    mut outgoing: mpsc::Receiver<Outgoing>,
+    updates: mpsc::Sender<Update>,
+) {
+    let mut pending: VecDeque<(u64, Message)> = VecDeque::new();
+    let mut failed: Vec<(u64, Message)> = Vec::new();
+    let mut backoff = Backoff::default();
+
+    // End of the wait before the next attempt (the backoff or the Retry-After of the server), and if it was
+    // asked by the server: the new messages are queued meanwhile instead of being sent right away
+    let mut deadline: Option<Instant> = None;
+    let mut rate_limited = false;
+
+    loop {
+        // Flush the pending messages, unless the wait before the next attempt is not over
+        if deadline.is_none_or(|deadline| Instant::now() >= deadline) {
+            let retry_in = flush(
+                socket_addr,
+                &mut pending,
+                &mut failed,
+                &mut backoff,
+                &updates,
+            )
+            .await;
+            deadline = retry_in.map(|(retry_in, _)| Instant::now() + retry_in);
+            rate_limited = retry_in.is_some_and(|(_, rate_limited)| rate_limited);
+        }
+
+        // Wait for a request of the tui, or for the next attempt if some messages are pending
+        let request = match deadline {
+            None => outgoing.recv().await,
+            Some(deadline) => select! {
+                request = outgoing.recv() => request,
+                _ = sleep_until(deadline) => continue,
+            },
+        };
+
+        // The task ends when the tui is closed
+        let Some(request) = request else {
+            return;
+        };
+
+        match request {
+            Outgoing::Send { id, message } => {
+                report(
+                    &updates,
+                    Update::Delivery {
+                        id,
+                        delivery: Delivery::Pending,
+                    },
+                )
+                .await;
+                pending.push_back((id, message));
+            }
+            Outgoing::Retry => {
+                for (id, message) in failed.drain(..) {
+                    report(
+                        &updates,
+                        Update::Delivery {
+                            id,
+                            delivery: Delivery::Pending,
+                        },
+                    )
+                    .await;
+                    pending.push_back((id, message));
+                }
+                // Only the wait of the backoff is skipped, the server asked to slow down
+                if !rate_limited {
+                    deadline = None;
+                }
+            }
+            Outgoing::Discard(id) => {
+                pending.retain(|(pending_id, _)| *pending_id != id);
+                failed.retain(|(failed_id, _)| *failed_id != id);
+            }
+        }
+    }
+}
+
+// Flush function sends the pending messages from the oldest one, stopping when the server is not reachable
+// or asks to slow down, and returns how long to wait before the next attempt (and if the server asked it)
+async fn flush(
+    socket_addr: SocketAddr,
+    pending: &mut VecDeque<(u64, Message)>,
+    failed: &mut Vec<(u64, Message)>,
+    backoff: &mut Backoff,
+    updates: &mpsc::Sender<Update>,
+) -> Option<(Duration, bool)> {
+    let mut reachable = true;
+    let mut slow_down = None;
+    while let Some((id, message)) = pending.pop_front() {
+        let delivery = match send_message(socket_addr, &message).await {
+            Ok(server_id) => Delivery::Sent(server_id),
+            Err(SendError::Rejected(reason)) => {
+                warn!(%reason, "message rejected by the server");
+                failed.push((id, message));
+                Delivery::Failed(reason)
+            }
+            Err(SendError::Unreachable(e)) => {
+                debug!(error = %e, pending = pending.len() + 1, "server unreachable, message kept pending");
+                pending.push_front((id, message));
+                reachable = false;
+                break;
+            }
+            Err(SendError::RateLimited(retry_after)) => {
+                info!("rate limited by the server, retrying in {retry_after:?}");
+                pending.push_front((id, message));
+                report(updates, Update::SlowDown(retry_after)).await;
+                slow_down = Some(retry_after);
+                break;
+            }
+        };
+        report(updates, Update::Delivery { id, delivery }).await;
+    }
+
+    if reachable {
+        if backoff.attempt() > 0 {
+            backoff.reset();
+            report(updates, Update::Connection(ConnectionStatus::Connected)).await;
+        }
+        slow_down.map(|retry_in| (retry_in, true))
+    } else {
+        let retry_in = backoff.next_delay();
+        let status = ConnectionStatus::Reconnecting {
+            attempt: backoff.attempt(),
+            retry_in,
+        };
+        report(updates, Update::Connection(status)).await;
+        Some((retry_in, false))
+    }
+}
+
+// Report function sends an update to the tui, ignoring the error if the tui is closed
+async fn report(updates: &mpsc::Sender<Update>, update: Update) {
+    let _ = updates.send(update).await;
+}
@@ -0,0 +1,50 @@
+/// Theme module: colors of the tui
+// Necessary imports
+use anyhow::anyhow;
+use chat_lib::config::Theme;
+use ratatui::style::Color;
+
+/// Palette struct containing the colors of the tui parsed from the theme of the configuration
+#[derive(Debug, Clone)]
+pub struct Palette {
+    /// Color of the borders and titles
+    pub border: Color,
+
+    /// Background of the messages sent by the local user
+    pub own_message: Color,
+
+    /// Background of the messages mentioning the local user
+    pub mention: Color,
+
+    /// Color of the status line
+    pub status: Color,
+
+    /// Colors assigned to the usernames
+    pub user_colors: Vec<Color>,
+}
+
+// TryFrom<&Theme> implementation for Palette struct: parses the color names of the theme
+impl TryFrom<&Theme> for Palette {
+    type Error = anyhow::Error;
+
+    fn try_from(theme: &Theme) -> Result<Self, Self::Error> {
+        Ok(Palette {
+            border: parse_color(&theme.border)?,
+            own_message: parse_color(&theme.own_message)?,
+            mention: parse_color(&theme.mention)?,
+            status: parse_color(&theme.status)?,
+            user_colors: theme
+                .user_colors
+                .iter()
+                .map(|color| parse_color(color))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+// Parse color function parses a color name ("blue", "darkgray", "#rrggbb" or an ANSI index)
+fn parse_color(color: &str) -> Result<Color, anyhow::Error> {
+    color
+        .parse()
+        .map_err(|_| anyhow!("invalid color in theme: {color}"))
+}
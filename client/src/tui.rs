@@ -1,15 +1,50 @@
-use chat_lib::messages::Message;
+use crate::{
+    commands::{self, Command},
+    history::History,
+    keys::Keys,
+    net::{ConnectionStatus, Delivery, OutboxEntry, Outgoing, Update},
+    theme::Palette,
+};
+use anyhow::anyhow;
+use chat_lib::{
+    client::{export_messages, moderate, search_messages, send_admin_request},
+    config::{ClientConfig, TimestampFormat},
+    export::{ExportFormat, ExportQuery},
+    messages::{unix_now, Message, MessageKind, DEFAULT_ROOM},
+    moderation::Sanction,
+    request::encode_component,
+    validation::ValidationPolicy,
+};
+use chrono::{Local, TimeZone};
 /// TUI module to implement a tui with ratatui
 // Needed imports
 use ratatui::{
-    crossterm::event::{self, Event, KeyCode, KeyModifiers},
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Stylize},
-    widgets::{Block, Borders, List, Paragraph},
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
     DefaultTerminal, Frame,
 };
-use tokio::{io::AsyncWriteExt, net::TcpStream, sync::mpsc::Receiver};
-use tui_input::{backend::crossterm::EventHandler, Input};
+use std::{
+    io::{stdout, Write},
+    net::{IpAddr, SocketAddr},
+};
+use tokio::sync::{
+    mpsc::{self, Receiver},
+    watch::Sender,
+};
+use tui_input::{backend::crossterm::EventHandler, Input, InputRequest};
+
+/// Subscription struct describing which messages the receiver task has to fetch
+#[derive(Debug, Clone)]
+pub struct Subscription {
+    /// Username of the reader
+    pub user: String,
+
+    /// Current room
+    pub room: String,
+}
 
 #[derive(Debug)]
 pub struct App {
@@ -18,11 +53,165 @@ pub struct App {
     /// Username
     user: String,
 
+    /// Current room
+    room: String,
+
+    /// Rooms joined during the session
+    rooms: Vec<String>,
+
     /// User input
     input: Input,
 
     /// Chat messages
     messages: Vec<Message>,
+
+    /// How message timestamps are displayed
+    timestamps: TimestampFormat,
+
+    /// Messages older than this timestamp are hidden (/clear)
+    cleared_before: u64,
+
+    /// Text of the status line (command errors and notices)
+    status: String,
+
+    /// History of the sent lines
+    history: History,
+
+    /// Reverse search in the history (Ctrl-R), if active
+    search: Option<ReverseSearch>,
+
+    /// Number of messages of the room mentioning the user, None until the room is loaded
+    mentions: Option<usize>,
+
+    /// Whether new mentions ring the terminal bell
+    bell: bool,
+
+    /// Colors of the tui
+    palette: Palette,
+
+    /// Key bindings of the tui
+    keys: Keys,
+
+    /// Search through the chat history (Ctrl-F), if active
+    message_search: Option<MessageSearch>,
+
+    /// Id of the message selected in the list after a search
+    selected: Option<u64>,
+
+    /// State of the connection with the server
+    connection: ConnectionStatus,
+
+    /// Messages sent from the tui that are not yet shown by the server, with their delivery state
+    outbox: Vec<OutboxEntry>,
+
+    /// Local id of the last message added to the outbox
+    outbox_id: u64,
+
+    /// Rules the sent messages must follow, received from the server
+    policy: ValidationPolicy,
+
+    /// Token of the admin api, the admin commands are available if set
+    admin_token: Option<String>,
+}
+
+/// MessageSearch struct containing the state of a search through the chat history
+#[derive(Debug, Default)]
+struct MessageSearch {
+    /// Input of the searched text
+    input: Input,
+
+    /// Text of the last search sent to the server
+    searched: String,
+
+    /// Messages matching the last search
+    results: Vec<Message>,
+
+    /// Index of the selected result
+    selected: usize,
+}
+
+/// ReverseSearch struct containing the state of a reverse search in the history
+#[derive(Debug, Default)]
+struct ReverseSearch {
+    /// Searched text
+    query: String,
+
+    /// Index in the history of the current match
+    position: Option<usize>,
+
+    /// Input before the search started, restored if the search is cancelled
+    original: String,
+}
+
+/// User color function picks a color for a username from the palette, always the same for the same name
+///
+/// Args:
+///     - user: username
+///     - colors: colors assigned to the usernames
+fn user_color(user: &str, colors: &[Color]) -> Color {
+    // FNV-1a hash, stable across runs and platforms unlike the std hasher
+    let hash = user.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+
+    if colors.is_empty() {
+        return Color::Reset;
+    }
+
+    colors[(hash % colors.len() as u64) as usize]
+}
+
+/// Format timestamp function formats the timestamp of a message according to the chosen format
+///
+/// Args:
+///     - timestamp: unix timestamp of the message in seconds
+///     - format: display format of the timestamp
+fn format_timestamp(timestamp: u64, format: TimestampFormat) -> Option<String> {
+    match format {
+        TimestampFormat::None => None,
+        TimestampFormat::Relative => {
+            let elapsed = unix_now().saturating_sub(timestamp);
+            let relative = match elapsed {
+                0..60 => "now".to_string(),
+                60..3600 => format!("{}m", elapsed / 60),
+                3600..86400 => format!("{}h", elapsed / 3600),
+                _ => format!("{}d", elapsed / 86400),
+            };
+            Some(relative)
+        }
+        TimestampFormat::Absolute => Local
+            .timestamp_opt(timestamp as i64, 0)
+            .single()
+            .map(|time| time.format("%H:%M").to_string()),
+    }
+}
+
+/// Sanction function builds the sanction of a user or of an address (if the target is an ip address)
+///
+/// Args:
+///     - target: username or ip address
+///     - minutes: duration of the sanction, permanent if None
+fn sanction(target: &str, minutes: Option<u64>) -> Sanction {
+    let duration_secs = minutes.map(|minutes| minutes * 60);
+    match target.parse::<IpAddr>() {
+        Ok(ip) => Sanction {
+            ip: Some(ip),
+            duration_secs,
+            ..Default::default()
+        },
+        Err(_) => Sanction {
+            user: Some(target.to_string()),
+            duration_secs,
+            ..Default::default()
+        },
+    }
+}
+
+// For minutes function describes the duration of a sanction
+fn for_minutes(minutes: Option<u64>) -> String {
+    minutes
+        .map(|minutes| format!(" for {minutes} minutes"))
+        .unwrap_or_default()
 }
 
 // Methods for the App struct
@@ -31,34 +220,64 @@ impl App {
     ///
     /// Args:
     ///     - user: username
-    pub fn new(user: String) -> Self {
-        Self {
+    ///     - config: configuration of the client (timestamps, bell, theme and key bindings)
+    ///     - history: history of the sent lines
+    ///     - policy: rules the sent messages must follow
+    pub fn new(
+        user: String,
+        config: &ClientConfig,
+        history: History,
+        policy: ValidationPolicy,
+    ) -> Result<Self, anyhow::Error> {
+        Ok(Self {
             user,
             input: Input::default(),
             messages: vec![],
-        }
+            timestamps: config.timestamps,
+            room: DEFAULT_ROOM.to_string(),
+            rooms: vec![DEFAULT_ROOM.to_string()],
+            cleared_before: 0,
+            status: "Type /help for the list of commands".to_string(),
+            history,
+            search: None,
+            mentions: None,
+            bell: config.bell,
+            palette: Palette::try_from(&config.theme)?,
+            keys: Keys::try_from(&config.keybindings)?,
+            message_search: None,
+            selected: None,
+            connection: ConnectionStatus::Connected,
+            outbox: vec![],
+            outbox_id: 0,
+            policy,
+            admin_token: config.admin_token.clone(),
+        })
     }
 
     /// Run method runs the application in loop until it is stopped
     ///
     /// Args:
     ///     - terminal: the terminal instance
-    ///     - rx: receiver for the messages over the channel between the two tasks
+    ///     - rx: receiver for the updates of the network tasks
+    ///     - subscription: sender used to tell the receiver task the current user and room
+    ///     - outgoing: sender of the requests to the sender task
+    ///     - socket_addr: socket address of the server
     pub async fn run(
         mut self,
         terminal: &mut DefaultTerminal,
-        rx: &mut Receiver<Vec<Message>>,
-        socket_addr: &str,
+        rx: &mut Receiver<Update>,
+        subscription: &Sender<Subscription>,
+        outgoing: &mpsc::Sender<Outgoing>,
+        socket_addr: SocketAddr,
     ) -> Result<(), anyhow::Error> {
         // Main loop of the client
         loop {
             // Draw a frame on the terminal
             terminal.draw(|frame| self.draw(frame))?;
 
-            // Receives the messages and update the App's state
-            let msgs = rx.recv().await;
-            if msgs.is_some() {
-                self.messages = msgs.unwrap();
+            // Receives the updates of the network tasks without waiting, so that the tui stays responsive while disconnected
+            while let Ok(update) = rx.try_recv() {
+                self.update(update);
             }
 
             // Check if there's an event in an interval of 100ms
@@ -67,29 +286,89 @@ impl App {
 
                 // Handle the event
                 if let Event::Key(key) = event {
-                    match key.code {
-                        KeyCode::Enter => {
-                            // Connection to the server
-                            let mut stream = TcpStream::connect(socket_addr).await?;
+                    // While searching the history the keys edit the search
+                    if self.search.is_some() {
+                        self.search_key(key);
+                        continue;
+                    }
 
-                            // Get the message from the input and construct the POST request
-                            let message = self.input.value_and_reset();
-                            let body =
-                                format!(r#"{{"user":"{}", "message":"{message}"}}"#, self.user);
-                            let request = format!("POST /messages HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}", body.len());
+                    // While searching the messages the keys edit the search and move through the results
+                    if self.message_search.is_some() {
+                        self.message_search_key(key, &event, subscription, socket_addr)
+                            .await;
+                        continue;
+                    }
 
-                            // Send the POST request to the server
-                            stream.write_all(request.as_bytes()).await?;
+                    match key.code {
+                        // End the program (CTRL-C by default)
+                        _ if self.keys.quit.matches(&key) => return Ok(()),
+                        // Start a reverse search in the history
+                        _ if self.keys.history_search.matches(&key) => {
+                            self.search = Some(ReverseSearch {
+                                original: self.input.value().to_string(),
+                                ..Default::default()
+                            });
+                        }
+                        // Start a search through the chat history
+                        _ if self.keys.message_search.matches(&key) => {
+                            self.message_search = Some(MessageSearch::default())
+                        }
+                        _ if self.keys.complete.matches(&key) => self.complete(),
+                        // Send again the failed messages
+                        _ if self.keys.retry.matches(&key) => self.retry(outgoing).await?,
+                        // Drop the newest message not delivered yet
+                        _ if self.keys.discard.matches(&key) => self.discard(outgoing).await?,
+                        KeyCode::Enter => {
+                            // Get the text from the input, record it and check if it is a slash command
+                            let text = self.input.value_and_reset();
+                            self.history.push(&text);
+                            match commands::parse(&text, self.admin_token.is_some()) {
+                                Ok(Some(command)) => {
+                                    if self
+                                        .execute(command, subscription, outgoing, socket_addr)
+                                        .await?
+                                    {
+                                        return Ok(());
+                                    }
+                                }
+                                Ok(None) if text.is_empty() => {}
+                                Ok(None) => {
+                                    // A leading "//" sends a message starting with a slash
+                                    let text = text.strip_prefix('/').unwrap_or(&text);
+                                    let message = Message::new(&self.user, text, &self.room);
+                                    self.send(message, outgoing).await?;
+                                }
+                                Err(e) => {
+                                    // Errors are shown in the status line and the input is restored
+                                    self.status = e.to_string();
+                                    self.input = Input::new(text);
+                                }
+                            }
+                        }
+                        KeyCode::Esc => self.selected = None,
+                        KeyCode::Up => {
+                            // Previous line of the history
+                            if let Some(line) = self.history.previous(self.input.value()) {
+                                self.input = Input::new(line.to_string());
+                            }
                         }
-                        KeyCode::Char('c') => {
-                            // End the program if event is CTRL-C
-                            if key.modifiers.contains(KeyModifiers::CONTROL) {
-                                return Ok(());
-                            } else {
-                                // Write 'c' in the input
-                                self.input.handle_event(&event);
+                        KeyCode::Down => {
+                            // Next line of the history
+                            if let Some(line) = self.history.next() {
+                                self.input = Input::new(line.to_string());
                             }
                         }
+                        KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            // Emacs-style editing
+                            self.edit(c)
+                        }
+                        // The text of a message can't grow past the maximum length
+                        KeyCode::Char(_) if self.message_len() >= self.policy.max_message_len => {
+                            self.status = format!(
+                                "Message too long (max {} characters)",
+                                self.policy.max_message_len
+                            );
+                        }
                         _ => {
                             // Every other character is written in the input
                             self.input.handle_event(&event);
@@ -100,44 +379,779 @@ impl App {
         }
     }
 
+    /// Edit method applies an emacs-style editing binding to the input
+    ///
+    /// Args:
+    ///     - key: character pressed together with CTRL
+    fn edit(&mut self, key: char) {
+        let request = match key {
+            'a' => InputRequest::GoToStart,
+            'e' => InputRequest::GoToEnd,
+            'w' => InputRequest::DeletePrevWord,
+            'k' => InputRequest::DeleteTillEnd,
+            'u' => {
+                // Delete from the start of the line to the cursor
+                let rest = self
+                    .input
+                    .value()
+                    .chars()
+                    .skip(self.input.cursor())
+                    .collect();
+                self.input = Input::new(rest).with_cursor(0);
+                return;
+            }
+            _ => return,
+        };
+
+        self.input.handle(request);
+    }
+
+    /// Search key method handles a key pressed during a reverse search
+    ///
+    /// Args:
+    ///     - key: pressed key
+    fn search_key(&mut self, key: KeyEvent) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        // Esc, Ctrl-G and the quit key cancel the search and restore the input
+        if key.code == KeyCode::Esc
+            || ctrl && key.code == KeyCode::Char('g')
+            || self.keys.quit.matches(&key)
+        {
+            self.input = Input::new(std::mem::take(&mut search.original));
+            self.search = None;
+            self.status.clear();
+            return;
+        }
+
+        // Index before which the next match is looked for
+        let before = match key.code {
+            _ if self.keys.history_search.matches(&key) => {
+                search.position.unwrap_or(self.history.len())
+            }
+            KeyCode::Char(c) if !ctrl => {
+                search.query.push(c);
+                self.history.len()
+            }
+            KeyCode::Backspace => {
+                search.query.pop();
+                self.history.len()
+            }
+            _ => {
+                // Any other key accepts the match, which stays in the input
+                self.search = None;
+                self.status.clear();
+                return;
+            }
+        };
+
+        match self.history.search(&search.query, before) {
+            Some((position, line)) => {
+                search.position = Some(position);
+                self.input = Input::new(line.to_string());
+                self.status.clear();
+            }
+            None => self.status = format!("No match for \"{}\"", search.query),
+        }
+    }
+
+    /// Message search key method handles a key pressed during a search through the chat history
+    ///
+    /// Args:
+    ///     - key: pressed key
+    ///     - event: event of the key, used to edit the searched text
+    ///     - subscription: sender used to tell the receiver task the current user and room
+    ///     - socket_addr: socket address of the server
+    async fn message_search_key(
+        &mut self,
+        key: KeyEvent,
+        event: &Event,
+        subscription: &Sender<Subscription>,
+        socket_addr: SocketAddr,
+    ) {
+        let Some(search) = &mut self.message_search else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc => {
+                self.message_search = None;
+                self.status.clear();
+            }
+            KeyCode::Up => search.selected = search.selected.saturating_sub(1),
+            KeyCode::Down => {
+                search.selected = (search.selected + 1).min(search.results.len().saturating_sub(1))
+            }
+            KeyCode::Enter if search.input.value() != search.searched => {
+                let text = search.input.value().to_string();
+                self.search_messages(text, socket_addr).await;
+            }
+            KeyCode::Enter => {
+                // Enter on an unchanged search jumps to the selected message
+                if let Some(message) = search.results.get(search.selected).cloned() {
+                    self.message_search = None;
+                    self.jump_to(message, subscription);
+                }
+            }
+            _ => {
+                search.input.handle_event(event);
+            }
+        }
+    }
+
+    /// Search messages method sends a search to the server and shows the results,
+    /// a text between slashes (e.g. /h(i|ello)/) is a regular expression
+    ///
+    /// Args:
+    ///     - text: searched text
+    ///     - socket_addr: socket address of the server
+    async fn search_messages(&mut self, text: String, socket_addr: SocketAddr) {
+        let regex = text.strip_prefix('/').and_then(|t| t.strip_suffix('/'));
+        let results = match regex {
+            Some(pattern) if !pattern.is_empty() => {
+                search_messages(socket_addr, pattern, true, &self.user).await
+            }
+            _ => search_messages(socket_addr, &text, false, &self.user).await,
+        };
+
+        let search = self.message_search.get_or_insert_default();
+        search.input = Input::new(text.clone());
+        search.searched = text;
+        match results {
+            Ok(results) => {
+                self.status = format!(
+                    "{} results (Up/Down to select, Enter to jump, Esc to close)",
+                    results.len()
+                );
+                // The newest result is selected
+                search.selected = results.len().saturating_sub(1);
+                search.results = results;
+            }
+            Err(e) => {
+                self.status = e.to_string();
+                search.results.clear();
+            }
+        }
+    }
+
+    /// Export method saves the history of the current room visible to the user to a file,
+    /// the format is chosen by the extension of the file
+    ///
+    /// Args:
+    ///     - file: path of the file
+    ///     - hours: only the messages of the last hours are saved, if given
+    ///     - socket_addr: socket address of the server
+    async fn export(
+        &self,
+        file: &str,
+        hours: Option<u64>,
+        socket_addr: SocketAddr,
+    ) -> Result<(), anyhow::Error> {
+        let query = ExportQuery {
+            format: ExportFormat::from_path(file.as_ref())?,
+            room: Some(self.room.clone()),
+            reader: Some(self.user.clone()),
+            since: hours.map(|hours| unix_now().saturating_sub(hours * 3600)),
+            ..Default::default()
+        };
+
+        let transcript = export_messages(socket_addr, &query).await?;
+        tokio::fs::write(file, transcript).await?;
+
+        Ok(())
+    }
+
+    /// Jump to method shows a message in its context, switching room if needed
+    ///
+    /// Args:
+    ///     - message: message to show
+    ///     - subscription: sender used to tell the receiver task the current user and room
+    fn jump_to(&mut self, message: Message, subscription: &Sender<Subscription>) {
+        if message.room != self.room {
+            self.join(message.room, subscription);
+        }
+
+        self.cleared_before = 0;
+        self.selected = Some(message.id);
+        self.status = "Esc to deselect the message".to_string();
+    }
+
+    /// Join method switches to another room
+    ///
+    /// Args:
+    ///     - room: room to join
+    ///     - subscription: sender used to tell the receiver task the current user and room
+    fn join(&mut self, room: String, subscription: &Sender<Subscription>) {
+        self.status = format!("Joined #{room}");
+        if !self.rooms.contains(&room) {
+            self.rooms.push(room.clone());
+        }
+        self.room = room;
+        self.messages.clear();
+        self.cleared_before = 0;
+        self.mentions = None;
+        self.selected = None;
+        self.subscribe(subscription);
+    }
+
+    /// Execute method runs a slash command, returns true if the client has to quit
+    ///
+    /// Args:
+    ///     - command: command to execute
+    ///     - subscription: sender used to tell the receiver task the current user and room
+    ///     - outgoing: sender of the requests to the sender task
+    ///     - socket_addr: socket address of the server
+    async fn execute(
+        &mut self,
+        command: Command,
+        subscription: &Sender<Subscription>,
+        outgoing: &mpsc::Sender<Outgoing>,
+        socket_addr: SocketAddr,
+    ) -> Result<bool, anyhow::Error> {
+        match command {
+            Command::Nick(user) => {
+                if let Err(e) = self.policy.validate_name("Username", &user) {
+                    self.status = e.to_string();
+                    return Ok(false);
+                }
+                self.status = format!("You are now known as {user}");
+                self.user = user;
+                self.mentions = None;
+                self.subscribe(subscription);
+            }
+            Command::Join(room) => match self.policy.validate_name("Room name", &room) {
+                Ok(()) => self.join(room, subscription),
+                Err(e) => self.status = e.to_string(),
+            },
+            Command::Msg { to, text } => {
+                let mut message = Message::new(&self.user, &text, &self.room);
+                message.to = Some(to);
+                self.send(message, outgoing).await?;
+            }
+            Command::Me(action) => {
+                let mut message = Message::new(&self.user, &action, &self.room);
+                message.kind = MessageKind::Action;
+                self.send(message, outgoing).await?;
+            }
+            Command::Clear => {
+                self.cleared_before = unix_now();
+                self.status.clear();
+            }
+            Command::Help(name) => {
+                self.status = commands::help(name.as_deref(), self.admin_token.is_some())
+                    .unwrap_or_else(|e| e.to_string());
+            }
+            Command::Search(text) => self.search_messages(text, socket_addr).await,
+            Command::Export { file, hours } => {
+                self.status = self
+                    .export(&file, hours, socket_addr)
+                    .await
+                    .map(|_| format!("Saved the history of #{} to {file}", self.room))
+                    .unwrap_or_else(|e| format!("Export failed: {e}"));
+            }
+            Command::Quit => return Ok(true),
+            command @ (Command::Delete(_)
+            | Command::Mute { .. }
+            | Command::Unmute(_)
+            | Command::Ban { .. }
+            | Command::Unban(_)
+            | Command::ClearRoom(_)) => self.moderate(command, socket_addr).await,
+        }
+
+        Ok(false)
+    }
+
+    /// Moderate method runs an admin command through the admin api, the outcome is shown in the status line
+    ///
+    /// Args:
+    ///     - command: admin command to run
+    ///     - socket_addr: socket address of the server
+    async fn moderate(&mut self, command: Command, socket_addr: SocketAddr) {
+        let Some(token) = self.admin_token.as_deref() else {
+            self.status = "Admin commands require an admin token".to_string();
+            return;
+        };
+
+        let result = match command {
+            Command::Delete(id) => match id.or(self.selected) {
+                Some(id) => {
+                    let uri = format!("/admin/messages/{id}");
+                    send_admin_request(socket_addr, token, "DELETE", &uri, None)
+                        .await
+                        .map(|_| format!("Deleted message #{id}"))
+                }
+                None => Err(anyhow!("No message selected, use /delete <id>")),
+            },
+            Command::Mute { target, minutes } => {
+                moderate(socket_addr, token, "mute", &sanction(&target, minutes))
+                    .await
+                    .map(|_| format!("Muted {target}{}", for_minutes(minutes)))
+            }
+            Command::Unmute(target) => {
+                moderate(socket_addr, token, "unmute", &sanction(&target, None))
+                    .await
+                    .map(|_| format!("Unmuted {target}"))
+            }
+            Command::Ban { target, minutes } => {
+                moderate(socket_addr, token, "ban", &sanction(&target, minutes))
+                    .await
+                    .map(|_| format!("Banned {target}{}", for_minutes(minutes)))
+            }
+            Command::Unban(target) => {
+                moderate(socket_addr, token, "unban", &sanction(&target, None))
+                    .await
+                    .map(|_| format!("Unbanned {target}"))
+            }
+            Command::ClearRoom(room) => {
+                let room = room.unwrap_or_else(|| self.room.clone());
+                let uri = format!("/admin/rooms/{}", encode_component(&room));
+                send_admin_request(socket_addr, token, "DELETE", &uri, None)
+                    .await
+                    .map(|_| format!("Cleared #{room}"))
+            }
+            _ => return,
+        };
+
+        self.status = result.unwrap_or_else(|e| e.to_string());
+    }
+
+    /// Subscribe method tells the receiver task the current user and room
+    ///
+    /// Args:
+    ///     - subscription: sender of the subscription channel
+    fn subscribe(&self, subscription: &Sender<Subscription>) {
+        let _ = subscription.send(Subscription {
+            user: self.user.clone(),
+            room: self.room.clone(),
+        });
+    }
+
+    /// Update method applies an update of the network tasks to the App's state
+    ///
+    /// Args:
+    ///     - update: update to apply
+    fn update(&mut self, update: Update) {
+        match update {
+            Update::Messages(msgs) => {
+                // Messages of the previous room may still arrive right after a /join
+                self.messages = msgs
+                    .into_iter()
+                    .filter(|msg| msg.room == self.room)
+                    .collect();
+                self.update_mentions();
+
+                // The sent messages leave the outbox once the server shows them
+                let messages = &self.messages;
+                let room = &self.room;
+                self.outbox.retain(|entry| match entry.delivery {
+                    Delivery::Sent(id) => {
+                        &entry.message.room == room
+                            && id != 0
+                            && !messages.iter().any(|msg| msg.id == id)
+                    }
+                    _ => true,
+                });
+            }
+            Update::Connection(connection) => self.connection = connection,
+            Update::SlowDown(retry_after) => {
+                self.status = format!(
+                    "Slow down! You are sending messages too fast, retrying in {}s",
+                    retry_after.as_secs()
+                );
+            }
+            Update::Delivery { id, delivery } => {
+                if let Some(entry) = self.outbox.iter_mut().find(|entry| entry.id == id) {
+                    if let Delivery::Failed(reason) = &delivery {
+                        self.status = format!("Message not delivered: {reason}");
+                    }
+                    entry.delivery = delivery;
+                }
+            }
+        }
+    }
+
+    /// Message len method returns the number of characters of the message being typed (0 for the commands not sending one)
+    fn message_len(&self) -> usize {
+        commands::message_text(self.input.value()).map_or(0, |text| text.chars().count())
+    }
+
+    /// Send method adds a message to the outbox and hands it to the sender task
+    ///
+    /// Args:
+    ///     - message: message to send
+    ///     - outgoing: sender of the requests to the sender task
+    async fn send(
+        &mut self,
+        mut message: Message,
+        outgoing: &mpsc::Sender<Outgoing>,
+    ) -> Result<(), anyhow::Error> {
+        // The messages breaking the policy are not sent, the server would refuse them
+        if let Err(e) = self.policy.validate(&mut message) {
+            self.status = e.to_string();
+            return Ok(());
+        }
+
+        // The local time is shown until the server stamps the message
+        message.timestamp = unix_now();
+        self.outbox_id += 1;
+        self.outbox.push(OutboxEntry {
+            id: self.outbox_id,
+            message: message.clone(),
+            delivery: Delivery::Pending,
+        });
+
+        outgoing
+            .send(Outgoing::Send {
+                id: self.outbox_id,
+                message,
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Retry method asks the sender task to send again the failed messages and the pending ones right away
+    ///
+    /// Args:
+    ///     - outgoing: sender of the requests to the sender task
+    async fn retry(&mut self, outgoing: &mpsc::Sender<Outgoing>) -> Result<(), anyhow::Error> {
+        let undelivered = self
+            .outbox
+            .iter()
+            .filter(|entry| !matches!(entry.delivery, Delivery::Sent(_)))
+            .count();
+        if undelivered == 0 {
+            self.status = "No messages to retry".to_string();
+            return Ok(());
+        }
+
+        self.status = format!("Retrying {undelivered} messages");
+        outgoing.send(Outgoing::Retry).await?;
+        Ok(())
+    }
+
+    /// Discard method drops the newest message of the current room that was not delivered yet
+    ///
+    /// Args:
+    ///     - outgoing: sender of the requests to the sender task
+    async fn discard(&mut self, outgoing: &mpsc::Sender<Outgoing>) -> Result<(), anyhow::Error> {
+        let Some(position) = self.outbox.iter().rposition(|entry| {
+            entry.message.room == self.room && !matches!(entry.delivery, Delivery::Sent(_))
+        }) else {
+            self.status = "No messages to discard".to_string();
+            return Ok(());
+        };
+
+        let entry = self.outbox.remove(position);
+        self.status = format!("Discarded: {}", entry.message.message);
+        outgoing.send(Outgoing::Discard(entry.id)).await?;
+        Ok(())
+    }
+
+    /// Connection title method returns the right aligned title showing the state of the connection
+    /// and the number of messages not delivered yet
+    fn connection_title(&self) -> Line<'static> {
+        let mut title = match self.connection {
+            ConnectionStatus::Connected => Line::from(" connected ".green()),
+            ConnectionStatus::Reconnecting { attempt, retry_in } => Line::from(
+                format!(
+                    " reconnecting (attempt {attempt}, retry in {:.1}s) ",
+                    retry_in.as_secs_f32()
+                )
+                .red(),
+            ),
+        };
+        let pending = self
+            .outbox
+            .iter()
+            .filter(|entry| entry.delivery == Delivery::Pending)
+            .count();
+        if pending > 0 {
+            title.push_span(format!("- {pending} pending ").yellow());
+        }
+        let failed = self
+            .outbox
+            .iter()
+            .filter(|entry| matches!(entry.delivery, Delivery::Failed(_)))
+            .count();
+        if failed > 0 {
+            title.push_span(format!("- {failed} failed ").red());
+        }
+
+        title.right_aligned()
+    }
+
+    /// Update mentions method counts the messages mentioning the user and rings the bell for the new ones
+    fn update_mentions(&mut self) {
+        let count = self
+            .messages
+            .iter()
+            .filter(|msg| self.is_mention(msg))
+            .count();
+
+        // The bell does not ring for the mentions already there when the room is loaded
+        if self.bell && self.mentions.is_some_and(|mentions| count > mentions) {
+            print!("\x07");
+            let _ = stdout().flush();
+        }
+
+        self.mentions = Some(count);
+    }
+
+    /// Is mention method checks if a message from another user mentions the local user
+    ///
+    /// Args:
+    ///     - message: message to check
+    fn is_mention(&self, message: &Message) -> bool {
+        message.user != self.user && message.mentions(&self.user)
+    }
+
+    /// Complete method tab-completes the command or argument being typed in the input
+    fn complete(&mut self) {
+        let mut users: Vec<String> = self.messages.iter().map(|msg| msg.user.clone()).collect();
+        users.sort();
+        users.dedup();
+
+        match commands::complete(
+            self.input.value(),
+            &users,
+            &self.rooms,
+            self.admin_token.is_some(),
+        ) {
+            Some(completion) => {
+                if completion.candidates.len() > 1 {
+                    self.status = completion.candidates.join(" ");
+                }
+                self.input = Input::new(completion.input);
+            }
+            None => self.status = "No completions".to_string(),
+        }
+    }
+
+    /// Message item method builds the list item of a message, highlighted if sent by the local user or mentioning them
+    ///
+    /// Args:
+    ///     - message: message to render
+    ///     - delivery: delivery state of the message, if it is still in the outbox
+    fn message_item(&self, message: &Message, delivery: Option<&Delivery>) -> ListItem<'_> {
+        let mut line = self.message_line(message);
+
+        // The delivery state is shown beside the messages of the local user
+        let marker = match delivery {
+            Some(Delivery::Pending) => Some(Span::from(" ⋯ pending").yellow()),
+            Some(Delivery::Failed(reason)) => {
+                Some(Span::from(format!(" ✗ failed: {reason}")).red())
+            }
+            Some(Delivery::Sent(_)) => Some(Span::from(" ✓").green()),
+            None if message.user == self.user => Some(Span::from(" ✓").green()),
+            None => None,
+        };
+        if let Some(marker) = marker {
+            line.push_span(marker.not_bold());
+        }
+
+        if message.user == self.user {
+            ListItem::new(line).bg(self.palette.own_message)
+        } else if self.is_mention(message) {
+            ListItem::new(line).bg(self.palette.mention)
+        } else {
+            ListItem::new(line)
+        }
+    }
+
+    /// Message line method builds the line of a message: timestamp, colored username and content
+    ///
+    /// Args:
+    ///     - message: message to render
+    fn message_line(&self, message: &Message) -> Line<'_> {
+        let mut spans = vec![];
+
+        // The admins see the ids of the messages, used by /delete
+        if self.admin_token.is_some() && message.id != 0 {
+            spans.push(Span::styled(
+                format!("#{} ", message.id),
+                Style::new()
+                    .fg(Color::DarkGray)
+                    .remove_modifier(Modifier::BOLD),
+            ));
+        }
+
+        if let Some(time) = format_timestamp(message.timestamp, self.timestamps) {
+            spans.push(Span::styled(
+                format!("[{time:>5}] "),
+                Style::new()
+                    .fg(Color::DarkGray)
+                    .remove_modifier(Modifier::BOLD),
+            ));
+        }
+
+        let text_style = Style::new()
+            .fg(Color::Reset)
+            .remove_modifier(Modifier::BOLD);
+        let user = Span::styled(
+            message.user.clone(),
+            Style::new().fg(user_color(&message.user, &self.palette.user_colors)),
+        );
+
+        match (message.kind, &message.to) {
+            (MessageKind::Action, _) => {
+                spans.push(Span::styled("* ", text_style));
+                spans.push(user.italic());
+                spans.push(Span::styled(
+                    format!(" {}", message.message),
+                    text_style.italic(),
+                ));
+            }
+            (MessageKind::Text, Some(to)) => {
+                spans.push(user);
+                spans.push(Span::styled(" -> ", text_style));
+                spans.push(Span::styled(
+                    to.clone(),
+                    Style::new().fg(user_color(to, &self.palette.user_colors)),
+                ));
+                spans.push(Span::styled(
+                    format!(" (direct): {}", message.message),
+                    text_style,
+                ));
+            }
+            (MessageKind::Text, None) => {
+                spans.push(user);
+                spans.push(Span::styled(format!(": {}", message.message), text_style));
+            }
+        }
+
+        Line::from(spans)
+    }
+
     /// Draw function draws the frame on the terminal
     ///
     /// Args:
     ///     - frame: frame to render
     fn draw(&self, frame: &mut Frame<'_>) {
-        // Layout of the tui with two areas: one for the messages and the other for the input
-        let [messages_area, input_area] = Layout::default()
+        // Layout of the tui with three areas: one for the messages, one for the input and the status line
+        let [messages_area, input_area, status_area] = Layout::default()
             .direction(Direction::Vertical)
             .margin(1)
-            .constraints(vec![Constraint::Percentage(85), Constraint::Percentage(15)])
+            .constraints(vec![
+                Constraint::Min(3),
+                Constraint::Length(3),
+                Constraint::Length(1),
+            ])
             .areas(frame.area());
 
-        // Render messages
-        let messages = self
+        // Render the search results in place of the messages
+        if let Some(search) = &self.message_search {
+            let results = search.results.iter().map(|message| {
+                let mut line = self.message_line(message);
+                line.spans
+                    .insert(0, Span::raw(format!("#{} ", message.room)));
+                ListItem::new(line)
+            });
+            let mut state = ListState::default().with_selected(Some(search.selected));
+
+            frame.render_stateful_widget(
+                List::new(results)
+                    .highlight_style(Style::new().reversed())
+                    .block(
+                        Block::bordered()
+                            .fg(self.palette.border)
+                            .title("Search results"),
+                    ),
+                messages_area,
+                &mut state,
+            );
+        } else {
+            self.draw_messages(frame, messages_area);
+        }
+
+        // Render input (the searched text while searching the messages)
+        let (input, title) = match (&self.message_search, &self.search) {
+            (Some(search), _) => (&search.input, "Search messages".to_string()),
+            (None, Some(search)) => (
+                &self.input,
+                format!("(reverse-i-search) `{}`", search.query),
+            ),
+            // The length of the message is shown when it gets close to the maximum
+            (None, None) => match self.message_len() {
+                len if len * 5 >= self.policy.max_message_len * 4 => (
+                    &self.input,
+                    format!("Input ({len}/{})", self.policy.max_message_len),
+                ),
+                _ => (&self.input, "Input".to_string()),
+            },
+        };
+        let width = input_area.width.max(3) - 3;
+        let scroll = input.visual_scroll(width as usize);
+        let paragraph = Paragraph::new(input.value())
+            .scroll((0, scroll as u16))
+            .block(Block::bordered().title(title));
+        frame.render_widget(paragraph, input_area);
+
+        let x = input.visual_cursor().max(scroll) - scroll + 1;
+        frame.set_cursor_position((input_area.x + x as u16, input_area.y + 1));
+
+        // Render status line
+        frame.render_widget(
+            Paragraph::new(self.status.as_str()).fg(self.palette.status),
+            status_area,
+        );
+    }
+
+    /// Draw messages method renders the messages of the room (the ones hidden by /clear are skipped)
+    ///
+    /// Args:
+    ///     - frame: frame to render
+    ///     - area: area of the messages
+    fn draw_messages(&self, frame: &mut Frame<'_>, area: Rect) {
+        let messages: Vec<&Message> = self
             .messages
             .iter()
-            .map(|message| format!("{}: {}", message.user, message.message));
+            .filter(|message| message.timestamp >= self.cleared_before)
+            .collect();
 
-        frame.render_widget(
-            List::new(messages).block(
+        // The list is scrolled to the selected message, or to the newest one
+        let selected = self
+            .selected
+            .and_then(|id| messages.iter().position(|message| message.id == id));
+        let highlight = if selected.is_some() {
+            Style::new().reversed()
+        } else {
+            Style::new()
+        };
+
+        // The messages of the outbox follow the ones shown by the server
+        let outbox = self.outbox.iter().filter(|entry| {
+            entry.message.room == self.room
+                && entry.message.timestamp >= self.cleared_before
+                && !matches!(entry.delivery, Delivery::Sent(id) if messages.iter().any(|msg| msg.id == id))
+        });
+
+        let items = messages
+            .iter()
+            .map(|message| self.message_item(message, None))
+            .chain(outbox.map(|entry| self.message_item(&entry.message, Some(&entry.delivery))))
+            .collect::<Vec<_>>();
+        let mut state = ListState::default().with_selected(selected.or(items.len().checked_sub(1)));
+
+        frame.render_stateful_widget(
+            List::new(items).highlight_style(highlight).block(
                 Block::new()
                     .bold()
-                    .fg(Color::Blue)
+                    .fg(self.palette.border)
                     .borders(Borders::ALL)
-                    .title("Messages"),
+                    .title(match self.mentions {
+                        Some(mentions) if mentions > 0 => format!(
+                            "Messages - #{} ({}) - {mentions} mentions",
+                            self.room, self.user
+                        ),
+                        _ => format!("Messages - #{} ({})", self.room, self.user),
+                    })
+                    .title(self.connection_title()),
             ),
-            messages_area,
+            area,
+            &mut state,
         );
-
-        // Render input
-        let width = input_area.width.max(3) - 3;
-        let scroll = self.input.visual_scroll(width as usize);
-        let input = Paragraph::new(self.input.value())
-            .scroll((0, scroll as u16))
-            .block(Block::bordered().title("Input"));
-        frame.render_widget(input, input_area);
-
-        let x = self.input.visual_cursor().max(scroll) - scroll + 1;
-        frame.set_cursor_position((input_area.x + x as u16, input_area.y + 1))
     }
 }
@@ -0,0 +1,29 @@
+/// Server args module
+// Necessary imports
+use chat_lib::args::Args;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// Server args struct representing the cli arguments of the server
+#[derive(StructOpt, Debug)]
+#[structopt(name = "Chat-TUI", about = "Server of the chat")]
+pub struct ServerArgs {
+    /// Arguments shared with the client (ip, port and config file)
+    #[structopt(flatten)]
+    pub args: Args,
+
+    /// Command: Run a maintenance command instead of the server
+    #[structopt(subcommand)]
+    pub command: Option<ServerCommand>,
+}
+
+// ServerCommand enum enumerates the maintenance commands of the server (a plain comment: structopt would show a doc comment in the help)
+#[derive(StructOpt, Debug)]
+pub enum ServerCommand {
+    /// Import the messages of a JSON Lines transcript into the store, keeping their ids (fails while the server is running)
+    Import {
+        /// File: JSON Lines transcript, as written by the export
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+    },
+}
@@ -1,46 +1,226 @@
 /// Main function for the chat app server
 // Necessary imports
-use chat_lib::{args::Args, handle_connection, messages::State, IP_ADDR, PORT};
-use std::sync::Arc;
+use args::{ServerArgs, ServerCommand};
+use chat_lib::{
+    bind_listener,
+    config::ServerConfig,
+    context::Context,
+    filter::FilterPipeline,
+    handle_connection,
+    hooks::HookRegistry,
+    import::import_file,
+    incoming::IncomingWebhooks,
+    logging,
+    messages::State,
+    metrics::Metrics,
+    moderation::Moderation,
+    rate_limit::RateLimits,
+    reject_connection,
+    retention::retention_task,
+    shutdown::{self, Shutdown},
+    storage::Store,
+    webhook::Webhooks,
+    ACCEPT_BACKOFF, MAX_REJECTIONS,
+};
+use std::{
+    sync::{atomic::AtomicU64, Arc},
+    time::Duration,
+};
 use structopt::StructOpt;
-use tokio::{net::TcpListener, sync::Mutex};
-use tracing::info;
+use tokio::{
+    net::TcpStream,
+    select,
+    sync::{Mutex, Semaphore},
+    task::JoinSet,
+    time::sleep,
+};
+use tracing::{info, warn};
+
+mod args;
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
-    tracing_subscriber::fmt::init(); // Logging
+    // Configuration from the config file, the environment variables and the cli args
+    let server_args = ServerArgs::from_args();
+    let config = ServerConfig::load(&server_args.args)?;
+    logging::init(&config.log)?; // Logging
 
-    // State of the server, containes all the messages
-    let state: State = Arc::new(Mutex::new(Vec::new()));
-
-    // Cli args
-    let Args { ip, port } = Args::from_args();
-    let ip_addr = if ip.is_some() {
-        ip.unwrap()
-    } else {
-        IP_ADDR.to_string()
-    };
+    // The maintenance commands work on the store and exit
+    if let Some(ServerCommand::Import { file }) = &server_args.command {
+        let report = import_file(&config, file).await?;
+        info!(
+            "Imported {} messages from {}, rejected {} duplicates {:?}",
+            report.imported,
+            file.display(),
+            report.duplicates.len(),
+            report.duplicates
+        );
+        return Ok(());
+    }
 
-    let port = if port.is_some() {
-        port.unwrap()
-    } else {
-        PORT.to_string()
+    // The saved messages are loaded if a persistent storage is configured
+    let (store, messages) = match &config.storage.path {
+        Some(path) => {
+            let (store, messages) = Store::open(path).await?;
+            info!("Loaded {} messages from {}", messages.len(), path.display());
+            (Some(store), messages)
+        }
+        None => (None, Vec::new()),
     };
+
+    // State of the server, containes all the messages
+    let last_id = AtomicU64::new(messages.last().map_or(0, |last| last.id));
+    let state: State = Arc::new(Mutex::new(messages));
+
+    // Content filters of the sent messages
+    let filters = Arc::new(FilterPipeline::load(config.filters.path.as_deref())?);
+    if let Some(path) = &config.filters.path {
+        info!("Loaded the filters from {}", path.display());
+    }
+
+    // Hooks of the messages, the extensions of the server are registered here
+    let mut hooks = HookRegistry::default();
+    hooks.register(Arc::clone(&filters) as _);
+    let webhooks = Webhooks::start(&config.webhooks)?;
+    if !webhooks.is_empty() {
+        hooks.register(Arc::new(webhooks));
+    }
+    info!("Registered hooks: {:?}", hooks.names());
+
+    // Mutes and bans decided through the admin api
+    let moderation = Moderation::load(config.admin.sanctions_path.as_deref())?;
+
+    // Incoming webhooks created through the admin api
+    let incoming = IncomingWebhooks::load(config.incoming_webhooks.path.as_deref())?;
+
     // Listening for connection at the default or specified socket address
-    let socket_addr = format!("{ip_addr}:{port}");
-    let listener = TcpListener::bind(socket_addr).await?;
+    let listener = bind_listener(config.socket_addr(), config.dual_stack)?;
     info!("Listening on: {}", listener.local_addr()?);
 
-    // Main loop of the server, it accepts connection until it is stopped
+    // Context shared by all the connections
+    let ctx = Arc::new(Context {
+        state,
+        store,
+        shutdown: Shutdown::default(),
+        rate_limits: RateLimits::from(&config.rate_limit),
+        moderation,
+        filters,
+        hooks,
+        incoming,
+        metrics: Metrics::default(),
+        last_id,
+        config,
+    });
+
+    // The retention policy is applied in background
+    let _retention_handle = tokio::spawn(retention_task(Arc::clone(&ctx)));
+
+    // The filters are reloaded on SIGHUP
+    #[cfg(unix)]
+    let _reload_handle = tokio::spawn(chat_lib::filter::reload_task(Arc::clone(&ctx)));
+
+    // Permits of the connections handled at the same time, and of the connections being refused
+    let permits = Arc::new(Semaphore::new(ctx.config.limits.max_connections));
+    let rejections = Arc::new(Semaphore::new(MAX_REJECTIONS));
+
+    // Main loop of the server, it accepts connection until a termination signal is received
+    let mut connections = JoinSet::new();
+    let signal = shutdown::signal();
+    tokio::pin!(signal);
     loop {
-        let (stream, _) = listener.accept().await?;
+        select! {
+            accepted = listener.accept() => {
+                // The accept errors are usually transient (e.g. out of file descriptors under load),
+                // the server waits a moment and keeps going
+                let (stream, addr) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        warn!(?e, "failed to accept a connection");
+                        sleep(ACCEPT_BACKOFF).await;
+                        continue;
+                    }
+                };
+                let ctx = Arc::clone(&ctx);
+                match Arc::clone(&permits).try_acquire_owned() {
+                    // Spawn a tokio task for every connection, the permit is released when it ends
+                    Ok(permit) => {
+                        connections.spawn(async move {
+                            serve(stream, ctx).await;
+                            drop(permit);
+                        });
+                    }
+                    // When the server is saturated the connection is refused with 503,
+                    // under a flood even the refusals are limited and the connections beyond them are just closed
+                    Err(_) => {
+                        warn!(%addr, "too many connections, refusing");
+                        ctx.metrics.connection_rejected();
+                        if let Ok(permit) = Arc::clone(&rejections).try_acquire_owned() {
+                            tokio::spawn(async move {
+                                let _ = reject_connection(stream).await;
+                                drop(permit);
+                            });
+                        }
+                    }
+                }
+            }
+            // The finished connections are removed from the set
+            Some(_) = connections.join_next(), if !connections.is_empty() => {}
+            signal = &mut signal => {
+                info!("Received {}, shutting down", signal?);
+                break;
+            }
+        }
+    }
 
-        let state = Arc::clone(&state);
-        // Spawn a tokio task for every connection
-        tokio::spawn(async move {
-            if let Err(e) = handle_connection(stream, state).await {
-                info!(?e, "failed to handle socket")
+    // The running connections are given some time to finish, while the new ones are told that the server is shutting down
+    ctx.shutdown.trigger();
+    info!("Waiting for {} connections to finish", connections.len());
+    let deadline = sleep(Duration::from_secs(ctx.config.shutdown.drain_timeout_secs));
+    tokio::pin!(deadline);
+    loop {
+        select! {
+            joined = connections.join_next() => {
+                if joined.is_none() {
+                    break;
+                }
             }
-        });
+            // The connections accepted while draining are answered with 503 by the server itself,
+            // they are bound by the same permits and deadline of the others
+            accepted = listener.accept() => {
+                match accepted {
+                    Ok((stream, _)) => {
+                        if let Ok(permit) = Arc::clone(&permits).try_acquire_owned() {
+                            let ctx = Arc::clone(&ctx);
+                            connections.spawn(async move {
+                                serve(stream, ctx).await;
+                                drop(permit);
+                            });
+                        }
+                    }
+                    Err(_) => sleep(ACCEPT_BACKOFF).await,
+                }
+            }
+            _ = &mut deadline => {
+                warn!("Aborting {} connections still running after the drain timeout", connections.len());
+                connections.shutdown().await;
+                break;
+            }
+        }
+    }
+    drop(listener);
+
+    // The messages are flushed to the disk before exiting
+    if let Some(store) = &ctx.store {
+        store.flush().await?;
+        info!("Flushed the messages to {}", store.path().display());
     }
+    info!("Server stopped");
+
+    Ok(())
+}
+
+// Serve function handles a connection, logging the error if it fails
+async fn serve(stream: TcpStream, ctx: Arc<Context>) {
+    // The errors are logged inside the span of the connection
+    let _ = handle_connection(stream, ctx).await;
 }